@@ -1,13 +1,39 @@
 use std::fs;
 use serde::{Serialize, Deserialize};
 
+use crate::crypto::sigcache::DEFAULT_SIGCACHE_ENTRIES;
+use crate::policy::PolicyConfig;
+
 const CONFIG_FILE: &str = "data/miner_config.json";
 
+/// Default TCP port for inbound peers
+pub const DEFAULT_P2P_PORT: u16 = 8333;
+
+fn default_p2p_port() -> u16 {
+    DEFAULT_P2P_PORT
+}
+
+fn default_sigcache_entries() -> usize {
+    DEFAULT_SIGCACHE_ENTRIES
+}
+
 /// Miner configuration (POLICY ONLY)
 #[derive(Serialize, Deserialize)]
 pub struct MinerConfig {
     /// Wallet name used for coinbase rewards
     pub coinbase_wallet: String,
+
+    /// Relay & block-assembly standardness rules
+    #[serde(default)]
+    pub policy: PolicyConfig,
+
+    /// TCP port accepting inbound peers
+    #[serde(default = "default_p2p_port")]
+    pub p2p_port: u16,
+
+    /// Signature cache capacity (0 disables the cache)
+    #[serde(default = "default_sigcache_entries")]
+    pub sigcache_entries: usize,
 }
 
 /// Load miner configuration from disk
@@ -23,6 +49,9 @@ pub fn load_miner_config() -> MinerConfig {
 
     let default = MinerConfig {
         coinbase_wallet: "default".to_string(),
+        policy: PolicyConfig::default(),
+        p2p_port: DEFAULT_P2P_PORT,
+        sigcache_entries: DEFAULT_SIGCACHE_ENTRIES,
     };
 
     fs::write(
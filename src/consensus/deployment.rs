@@ -0,0 +1,294 @@
+// ─────────────────────────────────────────────
+// CONSENSUS v6 — VERSION-BITS DEPLOYMENTS
+//
+// Miners signal readiness for a future rule change
+// by setting a bit in the block header version.
+//
+// State is evaluated once per retarget window:
+//
+//   DEFINED → STARTED → LOCKED_IN → ACTIVE
+//                 ↘ FAILED
+//
+// Signaling is informational: a deployment reaching
+// ACTIVE still needs its own version-gated rules.
+// ─────────────────────────────────────────────
+
+use serde::Serialize;
+
+use crate::core::block::Block;
+use crate::consensus::params::DIFFICULTY_ADJUSTMENT_INTERVAL;
+use crate::consensus::version::{
+    consensus_version_for_height,
+    ConsensusVersion,
+    CONSENSUS_V6_HEIGHT,
+};
+
+/// Header versions MUST carry these top bits (v6+)
+pub const VERSIONBITS_TOP_BITS: u32 = 0x2000_0000;
+pub const VERSIONBITS_TOP_MASK: u32 = 0xe000_0000;
+
+/// Blocks per evaluation window (= retarget window)
+pub const VERSIONBITS_WINDOW: u64 = DIFFICULTY_ADJUSTMENT_INTERVAL as u64;
+
+/// Signaling blocks needed in one window to lock in (90%)
+pub const VERSIONBITS_THRESHOLD: u64 = (VERSIONBITS_WINDOW * 9).div_ceil(10);
+
+/// A proposed rule change
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Deployment {
+    pub name: &'static str,
+    pub bit: u8,
+    /// First window (by start height) that may signal
+    pub start_height: u64,
+    /// Windows starting at or after this height FAIL if not locked in
+    pub timeout_height: u64,
+}
+
+/// Known deployments
+///
+/// `testdummy` exercises the machinery and carries no rules.
+pub const DEPLOYMENTS: &[Deployment] = &[
+    Deployment {
+        name: "testdummy",
+        bit: 28,
+        start_height: CONSENSUS_V6_HEIGHT,
+        timeout_height: CONSENSUS_V6_HEIGHT + 52_560,
+    },
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentState {
+    Defined,
+    Started,
+    LockedIn,
+    Active,
+    Failed,
+}
+
+/// True if a header version signals `bit`
+pub fn signals_bit(version: u32, bit: u8) -> bool {
+    version & VERSIONBITS_TOP_MASK == VERSIONBITS_TOP_BITS
+        && version & (1u32 << bit) != 0
+}
+
+/// Header version rule (CONSENSUS)
+///
+/// - before v6: version MUST be 0 (not committed)
+/// - v6+:       top bits MUST equal VERSIONBITS_TOP_BITS
+pub fn is_valid_header_version(version: u32, height: u64) -> bool {
+    if consensus_version_for_height(height) >= ConsensusVersion::V6 {
+        version & VERSIONBITS_TOP_MASK == VERSIONBITS_TOP_BITS
+    } else {
+        version == 0
+    }
+}
+
+fn window_start(height: u64) -> u64 {
+    height - height % VERSIONBITS_WINDOW
+}
+
+/// Signaling blocks for `bit` at heights [from, to)
+///
+/// `chain` starts at genesis, so height == index.
+fn count_signals(chain: &[Block], from: u64, to: u64, bit: u8) -> u64 {
+    let end = (to as usize).min(chain.len());
+    let start = (from as usize).min(end);
+
+    chain[start..end]
+        .iter()
+        .filter(|b| signals_bit(b.header.version, bit))
+        .count() as u64
+}
+
+/// State of `dep` for the block at `next_height`
+///
+/// `chain` is the chain ending at next_height - 1.
+pub fn deployment_state(
+    dep: &Deployment,
+    chain: &[Block],
+    next_height: u64,
+) -> DeploymentState {
+    let mut state = DeploymentState::Defined;
+    let mut start = 0u64;
+    let last_window = window_start(next_height);
+
+    while start < last_window {
+        let next = start + VERSIONBITS_WINDOW;
+
+        state = match state {
+            DeploymentState::Defined => {
+                if next >= dep.timeout_height {
+                    DeploymentState::Failed
+                } else if next >= dep.start_height {
+                    DeploymentState::Started
+                } else {
+                    DeploymentState::Defined
+                }
+            }
+            DeploymentState::Started => {
+                if next >= dep.timeout_height {
+                    DeploymentState::Failed
+                } else if count_signals(chain, start, next, dep.bit) >= VERSIONBITS_THRESHOLD {
+                    DeploymentState::LockedIn
+                } else {
+                    DeploymentState::Started
+                }
+            }
+            DeploymentState::LockedIn => DeploymentState::Active,
+            DeploymentState::Active => DeploymentState::Active,
+            DeploymentState::Failed => DeploymentState::Failed,
+        };
+
+        start = next;
+    }
+
+    state
+}
+
+/// Header version a miner should use at `next_height` (POLICY)
+///
+/// Signals every STARTED or LOCKED_IN deployment.
+pub fn compute_block_version(chain: &[Block], next_height: u64) -> u32 {
+    if consensus_version_for_height(next_height) < ConsensusVersion::V6 {
+        return 0;
+    }
+
+    let mut version = VERSIONBITS_TOP_BITS;
+
+    for dep in DEPLOYMENTS {
+        match deployment_state(dep, chain, next_height) {
+            DeploymentState::Started | DeploymentState::LockedIn => {
+                version |= 1u32 << dep.bit;
+            }
+            _ => {}
+        }
+    }
+
+    version
+}
+
+/// Upgrade progress for operators (API)
+#[derive(Debug, Clone, Serialize)]
+pub struct DeploymentStatus {
+    pub deployment: Deployment,
+    pub state: DeploymentState,
+    pub window_start: u64,
+    pub window_size: u64,
+    pub signals_in_window: u64,
+    pub threshold: u64,
+}
+
+/// Status of every deployment for the next block
+pub fn deployment_statuses(chain: &[Block], next_height: u64) -> Vec<DeploymentStatus> {
+    let start = window_start(next_height);
+
+    DEPLOYMENTS
+        .iter()
+        .map(|dep| DeploymentStatus {
+            deployment: *dep,
+            state: deployment_state(dep, chain, next_height),
+            window_start: start,
+            window_size: VERSIONBITS_WINDOW,
+            signals_in_window: count_signals(chain, start, next_height, dep.bit),
+            threshold: VERSIONBITS_THRESHOLD,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::block::BlockHeader;
+    use crate::consensus::params::MAX_TARGET;
+
+    const W: u64 = VERSIONBITS_WINDOW;
+
+    /// Starts signaling in window 1, fails from window 4
+    const DEP: Deployment = Deployment {
+        name: "test",
+        bit: 5,
+        start_height: W,
+        timeout_height: 4 * W,
+    };
+
+    /// Header-only chain of `len` blocks; `signal(height)`
+    /// decides whether each block sets DEP.bit
+    fn chain(len: u64, signal: impl Fn(u64) -> bool) -> Vec<Block> {
+        (0..len)
+            .map(|height| {
+                let mut version = VERSIONBITS_TOP_BITS;
+                if signal(height) {
+                    version |= 1 << DEP.bit;
+                }
+
+                Block {
+                    header: BlockHeader {
+                        version,
+                        height,
+                        timestamp: 0,
+                        prev_hash: Vec::new(),
+                        nonce: 0,
+                        target: MAX_TARGET,
+                        merkle_root: Vec::new(),
+                    },
+                    transactions: Vec::new(),
+                    hash: Vec::new(),
+                }
+            })
+            .collect()
+    }
+
+    fn state_at(chain: &[Block], next_height: u64) -> DeploymentState {
+        deployment_state(&DEP, &chain[..next_height as usize], next_height)
+    }
+
+    #[test]
+    fn defined_then_started() {
+        let c = chain(2 * W, |_| false);
+
+        assert_eq!(state_at(&c, 0), DeploymentState::Defined);
+        assert_eq!(state_at(&c, W - 1), DeploymentState::Defined);
+        assert_eq!(state_at(&c, W), DeploymentState::Started);
+    }
+
+    #[test]
+    fn locks_in_then_activates() {
+        // Window 1 signals at exactly the threshold
+        let c = chain(4 * W, |h| (W..W + VERSIONBITS_THRESHOLD).contains(&h));
+
+        assert_eq!(state_at(&c, 2 * W - 1), DeploymentState::Started);
+        assert_eq!(state_at(&c, 2 * W), DeploymentState::LockedIn);
+        assert_eq!(state_at(&c, 3 * W), DeploymentState::Active);
+        assert_eq!(state_at(&c, 4 * W), DeploymentState::Active);
+    }
+
+    #[test]
+    fn below_threshold_stays_started_then_fails() {
+        let c = chain(5 * W, |h| (W..W + VERSIONBITS_THRESHOLD - 1).contains(&h));
+
+        assert_eq!(state_at(&c, 2 * W), DeploymentState::Started);
+        assert_eq!(state_at(&c, 3 * W), DeploymentState::Started);
+        assert_eq!(state_at(&c, 4 * W), DeploymentState::Failed);
+        assert_eq!(state_at(&c, 5 * W), DeploymentState::Failed);
+    }
+
+    #[test]
+    fn signals_before_start_do_not_count() {
+        let c = chain(2 * W, |h| h < W);
+        assert_eq!(state_at(&c, 2 * W), DeploymentState::Started);
+    }
+
+    #[test]
+    fn header_version_rules() {
+        let bits = VERSIONBITS_TOP_BITS | (1 << DEP.bit);
+        assert!(signals_bit(bits, DEP.bit));
+        assert!(!signals_bit(VERSIONBITS_TOP_BITS, DEP.bit));
+        assert!(!signals_bit(1 << DEP.bit, DEP.bit));
+
+        assert!(is_valid_header_version(0, CONSENSUS_V6_HEIGHT - 1));
+        assert!(!is_valid_header_version(bits, CONSENSUS_V6_HEIGHT - 1));
+        assert!(is_valid_header_version(bits, CONSENSUS_V6_HEIGHT));
+        assert!(!is_valid_header_version(0, CONSENSUS_V6_HEIGHT));
+    }
+}
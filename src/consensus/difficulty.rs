@@ -32,6 +32,7 @@ fn clamp_target_big(target: BigUint) -> BigUint {
 ///
 /// Target calculation for blocks mined
 /// before CONSENSUS_V5_HEIGHT.
+#[allow(clippy::manual_is_multiple_of)]
 pub fn calculate_next_target(chain: &[Block]) -> [u8; 32] {
     // Genesis / empty chain
     if chain.is_empty() {
@@ -97,6 +98,7 @@ pub fn calculate_next_target(chain: &[Block]) -> [u8; 32] {
 ///
 /// Target for block at height `next_height`
 /// is computed using chain ending at height-1.
+#[allow(clippy::manual_is_multiple_of, clippy::unnecessary_cast)]
 pub fn calculate_next_target_v5(
     chain: &[Block],
     next_height: u64,
@@ -57,11 +57,13 @@ pub fn best_tip(blocks: &[Block]) -> Option<Vec<u8>> {
    (Structural + cumulative PoW)
    ───────────────────────────────────────────── */
 
-fn block_work(block: &Block) -> BigUint {
+/// Proof of work of a single block
+pub fn block_work(block: &Block) -> BigUint {
     block_work_from_target(&block.header.target)
 }
 
-fn cumulative_work(chain: &[Block]) -> BigUint {
+/// Total proof of work of a chain
+pub fn cumulative_work(chain: &[Block]) -> BigUint {
     let mut total = BigUint::zero();
     for b in chain {
         total += block_work(b);
@@ -70,7 +72,7 @@ fn cumulative_work(chain: &[Block]) -> BigUint {
 }
 
 /// Structural validation for v5 chains
-fn validate_chain_v5(chain: &[Block]) -> bool {
+pub fn validate_chain_v5(chain: &[Block]) -> bool {
     if chain.is_empty() {
         return false;
     }
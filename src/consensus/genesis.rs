@@ -10,6 +10,7 @@ use crate::revelation::revelation_tx;
 pub fn genesis_block() -> Block {
     Block {
         header: BlockHeader {
+            version: 0,
             height: 0,
             timestamp: 1730000000,        // ← REPLACE if different
             prev_hash: vec![0u8; 32],
@@ -8,4 +8,6 @@ pub mod params;
 pub mod fork_choice;
 pub mod serialize;
 pub mod genesis;
-pub mod version;
\ No newline at end of file
+pub mod version;
+pub mod deployment;
+pub mod shadow;
@@ -21,3 +21,20 @@ pub const MIN_TARGET: [u8; 32] = [
     0xff, 0xff, 0xff, 0xff,
     0xff, 0xff, 0xff, 0xff,
 ];
+
+/// Chain identity (CONSENSUS v6)
+///
+/// Version 2 sighashes commit to GENESIS_HASH and FORK_ID,
+/// so a signature made on one chain is invalid on any other.
+/// A fork sharing our history MUST pick a new FORK_ID.
+pub const GENESIS_HASH: [u8; 32] = [
+    0x8b, 0xdf, 0xff, 0x36,
+    0xf8, 0xf8, 0x0e, 0x04,
+    0x2e, 0x85, 0x77, 0x07,
+    0x68, 0xdf, 0x64, 0xf9,
+    0x5b, 0x61, 0xf9, 0xe5,
+    0xf5, 0x12, 0x8f, 0x4e,
+    0x49, 0x95, 0x5b, 0xce,
+    0x3e, 0x90, 0x2a, 0x1d,
+];
+pub const FORK_ID: u32 = 0;
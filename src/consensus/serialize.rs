@@ -8,8 +8,12 @@
 // Any change to this file is a HARD FORK.
 // ─────────────────────────────────────────────
 
-use crate::core::transaction::{Transaction, TxInput, TxOutput};
+use crate::core::transaction::{
+    Transaction, TxInput, TxOutput, OutputKind, TX_VERSION_LEGACY,
+};
 use crate::core::block::BlockHeader;
+use crate::consensus::params::{FORK_ID, GENESIS_HASH};
+use crate::consensus::version::{consensus_version_for_height, ConsensusVersion};
 
 // ───────── Primitive writers ─────────
 
@@ -30,9 +34,12 @@ fn write_bytes(bytes: &[u8], out: &mut Vec<u8>) {
     out.extend_from_slice(bytes);
 }
 
-// ───────── Block header (UNCHANGED) ─────────
+// ───────── Block header ─────────
 
 /// Serialize block header EXACTLY for hashing (CONSENSUS)
+///
+/// Pre-v6 bytes are UNCHANGED.
+/// v6+ appends the header version.
 pub fn serialize_block_header(header: &BlockHeader) -> Vec<u8> {
     let mut out = Vec::with_capacity(128);
 
@@ -43,19 +50,35 @@ pub fn serialize_block_header(header: &BlockHeader) -> Vec<u8> {
     out.extend_from_slice(&header.target);
     write_bytes(&header.merkle_root, &mut out);
 
+    if consensus_version_for_height(header.height) >= ConsensusVersion::V6 {
+        write_u32_le(header.version, &mut out);
+    }
+
     out
 }
 
 // ───────── Transaction serializers ─────────
 
-/// Serialize transaction for txid (CONSENSUS v5)
+/// Leading marker for non-legacy transactions (CONSENSUS v6)
+///
+/// A legacy serialization starts with the input count,
+/// which can never reach u32::MAX, so the two formats
+/// cannot produce the same preimage.
+const TX_VERSION_MARKER: u32 = u32::MAX;
+
+/// Serialize transaction for txid (CONSENSUS v5 / v6)
 ///
 /// EXCLUDES:
 /// - input.pubkey
 /// - input.signature
+///
+/// Legacy (version 1) bytes are UNCHANGED.
+/// Version 2+ adds: marker, version, output kind, lock_time.
 pub fn serialize_transaction_for_txid(tx: &Transaction) -> Vec<u8> {
     let mut out = Vec::new();
 
+    write_version_prefix(tx, &mut out);
+
     // Inputs
     write_u32_le(tx.inputs.len() as u32, &mut out);
     for i in &tx.inputs {
@@ -65,31 +88,70 @@ pub fn serialize_transaction_for_txid(tx: &Transaction) -> Vec<u8> {
     // Outputs
     write_u32_le(tx.outputs.len() as u32, &mut out);
     for o in &tx.outputs {
-        serialize_output(o, &mut out);
+        serialize_output(tx, o, &mut out);
     }
 
+    write_lock_time(tx, &mut out);
+
     out
 }
 
-/// Serialize transaction for sighash (CONSENSUS v5)
+/// Serialize transaction for sighash (CONSENSUS v5 / v6)
+///
+/// Legacy (version 1): INCLUDES full input context
 ///
-/// INCLUDES full input context
+/// Version 2+: a signature cannot commit to itself, so
+/// input pubkeys & signatures are EXCLUDED. Everything
+/// else (outpoints, outputs, lock_time) is committed,
+/// together with the chain identity (replay protection).
 pub fn serialize_transaction_for_sighash(tx: &Transaction) -> Vec<u8> {
     let mut out = Vec::new();
 
+    write_version_prefix(tx, &mut out);
+    write_chain_id(tx, &mut out);
+
     write_u32_le(tx.inputs.len() as u32, &mut out);
     for i in &tx.inputs {
-        serialize_input_full(i, &mut out);
+        if tx.version == TX_VERSION_LEGACY {
+            serialize_input_full(i, &mut out);
+        } else {
+            serialize_input_for_txid(i, &mut out);
+        }
     }
 
     write_u32_le(tx.outputs.len() as u32, &mut out);
     for o in &tx.outputs {
-        serialize_output(o, &mut out);
+        serialize_output(tx, o, &mut out);
     }
 
+    write_lock_time(tx, &mut out);
+
     out
 }
 
+// ───────── Version framing (v6) ─────────
+
+fn write_version_prefix(tx: &Transaction, out: &mut Vec<u8>) {
+    if tx.version != TX_VERSION_LEGACY {
+        write_u32_le(TX_VERSION_MARKER, out);
+        write_u32_le(tx.version, out);
+    }
+}
+
+/// Sighash only: txids stay chain-independent
+fn write_chain_id(tx: &Transaction, out: &mut Vec<u8>) {
+    if tx.version != TX_VERSION_LEGACY {
+        out.extend_from_slice(&GENESIS_HASH);
+        write_u32_le(FORK_ID, out);
+    }
+}
+
+fn write_lock_time(tx: &Transaction, out: &mut Vec<u8>) {
+    if tx.version != TX_VERSION_LEGACY {
+        write_u64_le(tx.lock_time, out);
+    }
+}
+
 // ───────── Input variants ─────────
 
 fn serialize_input_for_txid(i: &TxInput, out: &mut Vec<u8>) {
@@ -107,7 +169,153 @@ fn serialize_input_full(i: &TxInput, out: &mut Vec<u8>) {
 
 // ───────── Output ─────────
 
-fn serialize_output(o: &TxOutput, out: &mut Vec<u8>) {
+fn serialize_output(tx: &Transaction, o: &TxOutput, out: &mut Vec<u8>) {
     write_u64_le(o.value, out);
     write_bytes(&o.pubkey_hash, out);
+
+    // Legacy outputs carry no kind (always PubkeyHash)
+    if tx.version != TX_VERSION_LEGACY {
+        serialize_output_kind(&o.kind, out);
+    }
+}
+
+fn serialize_output_kind(kind: &OutputKind, out: &mut Vec<u8>) {
+    match kind {
+        OutputKind::PubkeyHash => out.push(0),
+        OutputKind::Multisig { required, pubkey_hashes } => {
+            out.push(1);
+            write_u32_le(*required, out);
+            write_u32_le(pubkey_hashes.len() as u32, out);
+            for h in pubkey_hashes {
+                write_bytes(h, out);
+            }
+        }
+        OutputKind::Htlc {
+            payment_hash,
+            claim_pubkey_hash,
+            refund_pubkey_hash,
+            timeout_height,
+        } => {
+            out.push(2);
+            write_bytes(payment_hash, out);
+            write_bytes(claim_pubkey_hash, out);
+            write_bytes(refund_pubkey_hash, out);
+            write_u64_le(*timeout_height, out);
+        }
+        OutputKind::DataCarrier { data } => {
+            out.push(3);
+            write_bytes(data, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::transaction::TX_VERSION_LOCKTIME;
+
+    fn tx(version: u32) -> Transaction {
+        Transaction {
+            version,
+            inputs: vec![TxInput {
+                txid: vec![1u8; 32],
+                index: 3,
+                pubkey: vec![2u8; 33],
+                signature: vec![4u8; 64],
+                address_index: 0,
+                signatures: Vec::new(),
+                preimage: Vec::new(),
+            }],
+            outputs: vec![TxOutput::pay_to_pubkey_hash(1_000, vec![5u8; 32])],
+            lock_time: 0,
+        }
+    }
+
+    #[test]
+    fn legacy_layout_unchanged() {
+        let t = tx(TX_VERSION_LEGACY);
+
+        // No marker, version, chain id or lock_time
+        let mut expected = Vec::new();
+        write_u32_le(1, &mut expected);
+        write_bytes(&[1u8; 32], &mut expected);
+        write_u32_le(3, &mut expected);
+        write_u32_le(1, &mut expected);
+        write_u64_le(1_000, &mut expected);
+        write_bytes(&[5u8; 32], &mut expected);
+
+        assert_eq!(serialize_transaction_for_txid(&t), expected);
+    }
+
+    #[test]
+    fn legacy_sighash_covers_signatures() {
+        let a = tx(TX_VERSION_LEGACY);
+        let mut b = a.clone();
+        b.inputs[0].signature = vec![9u8; 64];
+
+        assert_ne!(
+            serialize_transaction_for_sighash(&a),
+            serialize_transaction_for_sighash(&b)
+        );
+    }
+
+    #[test]
+    fn v2_sighash_commits_to_chain_identity() {
+        let bytes = serialize_transaction_for_sighash(&tx(TX_VERSION_LOCKTIME));
+
+        let mut prefix = Vec::new();
+        write_u32_le(TX_VERSION_MARKER, &mut prefix);
+        write_u32_le(TX_VERSION_LOCKTIME, &mut prefix);
+        prefix.extend_from_slice(&GENESIS_HASH);
+        write_u32_le(FORK_ID, &mut prefix);
+
+        assert!(bytes.starts_with(&prefix));
+    }
+
+    #[test]
+    fn v2_txid_is_chain_independent() {
+        let bytes = serialize_transaction_for_txid(&tx(TX_VERSION_LOCKTIME));
+
+        assert_eq!(&bytes[..4], &TX_VERSION_MARKER.to_le_bytes());
+        assert!(!bytes.windows(GENESIS_HASH.len()).any(|w| w == GENESIS_HASH));
+    }
+
+    #[test]
+    fn v2_sighash_excludes_signatures() {
+        let a = tx(TX_VERSION_LOCKTIME);
+        let mut b = a.clone();
+        b.inputs[0].pubkey = vec![3u8; 33];
+        b.inputs[0].signature = vec![9u8; 64];
+
+        assert_eq!(
+            serialize_transaction_for_sighash(&a),
+            serialize_transaction_for_sighash(&b)
+        );
+    }
+
+    #[test]
+    fn v2_commits_to_lock_time_and_output_kind() {
+        let a = tx(TX_VERSION_LOCKTIME);
+
+        let mut locked = a.clone();
+        locked.lock_time = 500;
+        assert_ne!(serialize_transaction_for_txid(&a), serialize_transaction_for_txid(&locked));
+        assert_ne!(
+            serialize_transaction_for_sighash(&a),
+            serialize_transaction_for_sighash(&locked)
+        );
+
+        let mut multisig = a.clone();
+        multisig.outputs[0] = TxOutput::multisig(1_000, 1, vec![vec![5u8; 32]]);
+        assert_ne!(serialize_transaction_for_txid(&a), serialize_transaction_for_txid(&multisig));
+    }
+
+    #[test]
+    fn formats_never_share_a_preimage() {
+        let legacy = serialize_transaction_for_sighash(&tx(TX_VERSION_LEGACY));
+        let v2 = serialize_transaction_for_sighash(&tx(TX_VERSION_LOCKTIME));
+
+        assert_ne!(&legacy[..4], &TX_VERSION_MARKER.to_le_bytes());
+        assert_ne!(legacy, v2);
+    }
 }
@@ -0,0 +1,240 @@
+// ─────────────────────────────────────────────
+// CONSENSUS v5 — SHADOW VALIDATION (DIAGNOSTIC)
+//
+// Replays the stored (v4-accepted) chain through
+// the v5 rules and reports every height where the
+// two rule sets disagree.
+//
+// Read-only. Never affects block acceptance.
+// ─────────────────────────────────────────────
+
+use serde::Serialize;
+
+use crate::core::block::Block;
+use crate::consensus::difficulty::{calculate_next_target, calculate_next_target_v5};
+use crate::consensus::fork_choice::{best_tip, select_best_chain_v5, validate_chain_v5};
+use crate::consensus::version::CONSENSUS_V5_HEIGHT;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisagreementKind {
+    /// v4 and v5 compute different targets
+    Target,
+    /// Header height differs from chain position (v4 derives
+    /// height from position, v5 trusts header.height)
+    Height,
+    /// v4 fork choice selects a tip v5 would reject
+    ForkChoice,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Disagreement {
+    pub height: u64,
+    pub kind: DisagreementKind,
+    pub v4: String,
+    pub v5: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShadowReport {
+    pub chain_height: u64,
+    pub configured_v5_height: u64,
+    /// Entire stored chain passes validate_chain_v5
+    pub v5_chain_valid: bool,
+    /// Longest prefix (in blocks) accepted by validate_chain_v5
+    pub v5_valid_prefix: u64,
+    /// Lowest activation height the stored history would not contradict
+    pub min_safe_activation_height: u64,
+    pub disagreements: Vec<Disagreement>,
+}
+
+/// Longest prefix passing validate_chain_v5
+///
+/// Prefix validity is monotone (an invalid prefix stays
+/// invalid when extended), so binary search suffices.
+fn v5_valid_prefix(chain: &[Block]) -> usize {
+    let (mut lo, mut hi) = (0usize, chain.len());
+
+    while lo < hi {
+        let mid = lo + (hi - lo).div_ceil(2);
+        if validate_chain_v5(&chain[..mid]) {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+
+    lo
+}
+
+/// Shadow-validate a stored chain (genesis first)
+///
+/// Only the active branch is stored, so fork choice is
+/// compared between that branch and its longest v5-valid
+/// prefix.
+pub fn shadow_validate_v5(chain: &[Block]) -> ShadowReport {
+    let mut disagreements = Vec::new();
+
+    for (i, block) in chain.iter().enumerate().skip(1) {
+        let height = block.header.height;
+
+        if height != i as u64 {
+            disagreements.push(Disagreement {
+                height: i as u64,
+                kind: DisagreementKind::Height,
+                v4: i.to_string(),
+                v5: height.to_string(),
+            });
+        }
+
+        let v4_target = calculate_next_target(&chain[..i]);
+        let v5_target = calculate_next_target_v5(&chain[..i], height);
+
+        if v4_target != v5_target {
+            disagreements.push(Disagreement {
+                height: i as u64,
+                kind: DisagreementKind::Target,
+                v4: hex::encode(v4_target),
+                v5: hex::encode(v5_target),
+            });
+        }
+    }
+
+    let valid_prefix = v5_valid_prefix(chain);
+
+    if valid_prefix < chain.len() {
+        let v4_tip = best_tip(chain).map(hex::encode).unwrap_or_default();
+
+        let candidates = vec![chain.to_vec(), chain[..valid_prefix].to_vec()];
+        let v5_tip = select_best_chain_v5(candidates)
+            .and_then(|c| c.last().map(|b| hex::encode(&b.hash)))
+            .unwrap_or_default();
+
+        disagreements.push(Disagreement {
+            height: valid_prefix as u64,
+            kind: DisagreementKind::ForkChoice,
+            v4: v4_tip,
+            v5: v5_tip,
+        });
+    }
+
+    disagreements.sort_by_key(|d| d.height);
+
+    let min_safe_activation_height = disagreements
+        .iter()
+        .map(|d| d.height + 1)
+        .max()
+        .unwrap_or(0);
+
+    ShadowReport {
+        chain_height: chain.len() as u64,
+        configured_v5_height: CONSENSUS_V5_HEIGHT,
+        v5_chain_valid: valid_prefix == chain.len(),
+        v5_valid_prefix: valid_prefix as u64,
+        min_safe_activation_height,
+        disagreements,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::block::BlockHeader;
+    use crate::consensus::params::{MAX_TARGET, TARGET_BLOCK_TIME};
+    use crate::pow::valid_pow;
+
+    /// Next block on `chain` with the given header height and target
+    fn mine(chain: &[Block], height: u64, target: [u8; 32]) -> Block {
+        let mut header = BlockHeader {
+            version: 0,
+            height,
+            timestamp: chain.len() as i64 * TARGET_BLOCK_TIME,
+            prev_hash: chain.last().map(|b| b.hash.clone()).unwrap_or_default(),
+            nonce: 0,
+            target,
+            merkle_root: Vec::new(),
+        };
+
+        while !valid_pow(&header.hash(), &header.target) {
+            header.nonce += 1;
+        }
+
+        Block { hash: header.hash(), header, transactions: Vec::new() }
+    }
+
+    /// `len` blocks, block `i` labelled `heights(i)` with `targets(i)`
+    fn chain(
+        len: usize,
+        heights: impl Fn(usize) -> u64,
+        targets: impl Fn(usize) -> [u8; 32],
+    ) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        for i in 0..len {
+            let block = mine(&blocks, heights(i), targets(i));
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    fn kinds(report: &ShadowReport) -> Vec<(u64, &'static str)> {
+        report
+            .disagreements
+            .iter()
+            .map(|d| {
+                let kind = match d.kind {
+                    DisagreementKind::Target => "target",
+                    DisagreementKind::Height => "height",
+                    DisagreementKind::ForkChoice => "fork_choice",
+                };
+                (d.height, kind)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn consistent_chain_has_no_disagreements() {
+        let blocks = chain(6, |i| i as u64, |_| MAX_TARGET);
+        let report = shadow_validate_v5(&blocks);
+
+        assert!(report.v5_chain_valid);
+        assert_eq!(report.v5_valid_prefix, 6);
+        assert!(report.disagreements.is_empty());
+        assert_eq!(report.min_safe_activation_height, 0);
+    }
+
+    #[test]
+    fn reports_height_mismatch() {
+        // Block 3 claims height 7
+        let blocks = chain(5, |i| if i == 3 { 7 } else { i as u64 }, |_| MAX_TARGET);
+        let report = shadow_validate_v5(&blocks);
+
+        assert!(!report.v5_chain_valid);
+        assert_eq!(report.v5_valid_prefix, 3);
+        assert_eq!(kinds(&report), vec![(3, "height"), (3, "fork_choice")]);
+
+        let height = &report.disagreements[0];
+        assert_eq!((height.v4.as_str(), height.v5.as_str()), ("3", "7"));
+
+        // v5 keeps the prefix ending at block 2
+        let fork = &report.disagreements[1];
+        assert_eq!(fork.v5, hex::encode(&blocks[2].hash));
+
+        assert_eq!(report.min_safe_activation_height, 4);
+    }
+
+    #[test]
+    fn reports_wrong_target() {
+        let mut harder = MAX_TARGET;
+        harder[0] = 0x7f;
+
+        // Block 2 mined at a target neither rule set expects
+        let blocks = chain(4, |i| i as u64, |i| if i == 2 { harder } else { MAX_TARGET });
+        let report = shadow_validate_v5(&blocks);
+
+        assert!(!report.v5_chain_valid);
+        assert_eq!(report.v5_valid_prefix, 2);
+        assert_eq!(kinds(&report), vec![(2, "fork_choice")]);
+        assert_eq!(report.disagreements[0].v5, hex::encode(&blocks[1].hash));
+        assert_eq!(report.min_safe_activation_height, 3);
+    }
+}
@@ -6,10 +6,11 @@
 // ─────────────────────────────────────────────
 
 /// Consensus versions
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ConsensusVersion {
     V4,
     V5,
+    V6,
 }
 
 /// 🔒 HARD FORK ACTIVATION HEIGHT
@@ -20,9 +21,31 @@ pub enum ConsensusVersion {
 /// Old nodes WILL fork here.
 pub const CONSENSUS_V5_HEIGHT: u64 = 50_000; // ← CHOOSE FINAL VALUE
 
+/// 🔒 HARD FORK ACTIVATION HEIGHT
+///
+/// Blocks at height >= CONSENSUS_V6_HEIGHT
+/// MUST be validated under Consensus v6:
+///
+/// - signatures MUST be low-S (normalized)
+/// - input pubkeys MUST be 33-byte compressed
+/// - version 2 (lock-time) transactions allowed
+/// - block transactions MUST be final (lock_time)
+///   and MUST spend valid outputs
+/// - m-of-n multisig outputs (version 2 only)
+/// - hash-time-locked outputs (version 2 only)
+/// - data-carrier outputs, never added to the UTXO set
+/// - block headers commit to a version-bits `version`
+/// - version 2 sighashes commit to the chain identity
+///   (GENESIS_HASH, FORK_ID)
+///
+/// Old nodes WILL fork here.
+pub const CONSENSUS_V6_HEIGHT: u64 = 100_000; // ← CHOOSE FINAL VALUE
+
 /// Determine consensus version for a block height
 pub fn consensus_version_for_height(height: u64) -> ConsensusVersion {
-    if height >= CONSENSUS_V5_HEIGHT {
+    if height >= CONSENSUS_V6_HEIGHT {
+        ConsensusVersion::V6
+    } else if height >= CONSENSUS_V5_HEIGHT {
         ConsensusVersion::V5
     } else {
         ConsensusVersion::V4
@@ -4,6 +4,10 @@ use crate::consensus::serialize::serialize_block_header;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BlockHeader {
+    /// Version-bits signaling (CONSENSUS v6, 0 before)
+    #[serde(default)]
+    pub version: u32,
+
     pub height: u64,
     pub timestamp: i64,
     pub prev_hash: Vec<u8>,
@@ -19,11 +23,18 @@ pub struct Block {
     pub hash: Vec<u8>,
 }
 
+impl BlockHeader {
+    /// Header hash (CONSENSUS)
+    pub fn hash(&self) -> Vec<u8> {
+        let bytes = serialize_block_header(self);
+        crate::crypto::sha256(&crate::crypto::sha256(&bytes))
+    }
+}
+
 impl Block {
     /// Block header hash (CONSENSUS)
     pub fn hash_header(&self) -> Vec<u8> {
-        let bytes = serialize_block_header(&self.header);
-        crate::crypto::sha256(&crate::crypto::sha256(&bytes))
+        self.header.hash()
     }
 
     pub fn verify_pow(&self) -> bool {
@@ -1,28 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::env;
 use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
 
 use time::OffsetDateTime;
 
 use crate::consensus::{
+    deployment::is_valid_header_version,
     difficulty::calculate_next_target,
-    fork_choice,
+    fork_choice::{self, cumulative_work},
     params::*,
 };
 
 use crate::core::{
     block::{Block, BlockHeader},
-    utxo::{UTXOSet, UTXO},
+    utxo::{UTXOSet, apply_transaction},
     transaction::Transaction,
+    validation::validate_block_transactions,
 };
 use crate::revelation::revelation_tx;
 use crate::merkle::merkle_root;
+use crate::pow::valid_pow;
 
 
 /* ───────── Persistence helpers ───────── */
 
-fn data_dir() -> PathBuf {
+pub fn data_dir() -> PathBuf {
     let mut path = env::current_exe().unwrap();
     path.pop();
     path.push("data");
@@ -41,7 +45,8 @@ fn utxos_file() -> PathBuf {
     path
 }
 
-fn median_time_past(chain: &[Block]) -> i64 {
+/// Median timestamp of the last MTP_WINDOW blocks
+pub fn median_time_past(chain: &[Block]) -> i64 {
     let mut times: Vec<i64> = chain
         .iter()
         .rev()
@@ -53,12 +58,120 @@ fn median_time_past(chain: &[Block]) -> i64 {
     times[times.len() / 2]
 }
 
+/// Replay a chain from genesis into a UTXO set
+fn utxos_for(blocks: &[Block]) -> UTXOSet {
+    let mut utxos = HashMap::new();
+
+    for block in blocks {
+        for (tx_index, tx) in block.transactions.iter().enumerate() {
+            let is_coinbase = tx_index == 0 && tx.inputs.is_empty();
+            apply_transaction(&mut utxos, tx, block.header.height, is_coinbase);
+        }
+    }
+
+    utxos
+}
+
+/// Hashes identifying a chain to a peer: the last 10 blocks,
+/// then exponentially sparser, always ending with genesis
+///
+/// The peer finds the highest hash it shares with us, so
+/// history after a fork is never assumed to be common.
+pub fn block_locator(chain: &[Block]) -> Vec<Vec<u8>> {
+    let mut locator = Vec::new();
+    if chain.is_empty() {
+        return locator;
+    }
+
+    let mut step = 1;
+    let mut i = chain.len() - 1;
+
+    loop {
+        locator.push(chain[i].hash.clone());
+        if i == 0 {
+            break;
+        }
+
+        if locator.len() >= 10 {
+            step *= 2;
+        }
+        i = i.saturating_sub(step);
+    }
+
+    locator
+}
+
+/// Header rules for a block on top of `prev`
+///
+/// Version, timestamp, difficulty and proof of work;
+/// shared by full blocks and headers-first sync.
+pub fn check_header(prev: &[Block], header: &BlockHeader, hash: &[u8]) -> bool {
+    // Header version (version bits, v6+)
+    if !is_valid_header_version(header.version, header.height) {
+        return false;
+    }
+
+    // Timestamp rules
+    if !prev.is_empty() {
+        let mtp = median_time_past(prev);
+        if header.timestamp <= mtp {
+            return false;
+        }
+
+        if header.timestamp >
+            OffsetDateTime::now_utc().unix_timestamp() + MAX_FUTURE_DRIFT
+        {
+            return false;
+        }
+    }
+
+    // Difficulty check
+    if header.target != calculate_next_target(prev) {
+        return false;
+    }
+
+    // PoW check
+    hash == header.hash().as_slice() && valid_pow(hash, &header.target)
+}
+
+/* ───────── Chain events ───────── */
+
+/// Active-chain changes, in the order they happen
+///
+/// On a reorg every disconnected block (tip first) is
+/// reported before the blocks of the new branch (oldest first).
+#[derive(Clone)]
+pub enum ChainEvent {
+    BlockConnected(Block),
+    BlockDisconnected(Block),
+}
+
+/* ───────── Branch connection ───────── */
+
+/// Why `connect_branch` left the active chain unchanged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BranchError {
+    /// The branch does not fork off the active chain
+    UnknownParent,
+    /// The block at this index of the branch is invalid
+    Invalid(usize),
+    /// Valid as far as checked, but not more work than the active chain
+    InsufficientWork,
+}
+
 /* ───────── Blockchain ───────── */
 
 pub struct Blockchain {
     pub blocks: Vec<Block>,
     pub utxos: UTXOSet,
     pub mempool: Vec<Transaction>,
+    subscribers: Vec<Sender<ChainEvent>>,
+}
+
+impl Default for Blockchain {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Blockchain {
@@ -67,9 +180,23 @@ impl Blockchain {
             blocks: Vec::new(),
             utxos: HashMap::new(),
             mempool: Vec::new(),
+            subscribers: Vec::new(),
         }
     }
 
+    /// Receive connect / disconnect notifications
+    pub fn subscribe(&mut self) -> Receiver<ChainEvent> {
+        let (tx, rx) = channel();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    fn notify(&mut self, events: Vec<ChainEvent>) {
+        self.subscribers.retain(|s| {
+            events.iter().all(|e| s.send(e.clone()).is_ok())
+        });
+    }
+
     pub fn height(&self) -> u64 {
         self.blocks.len() as u64
     }
@@ -84,9 +211,12 @@ impl Blockchain {
             }
         }
 
-        if self.blocks.is_empty() {
+        let created = self.blocks.is_empty();
+
+        if created {
             let genesis = Block {
                 header: BlockHeader {
+                    version: 0,
                     height: 0,
                     timestamp: 1730000000,
                     prev_hash: vec![0u8; 32],
@@ -97,9 +227,7 @@ impl Blockchain {
                     ).unwrap(),
                 },
                 transactions: vec![revelation_tx()],
-                hash: hex::decode(
-                    "8bdfff36f8f80e042e85770768df64f95b61f9e5f5128f4e49955bce3e902a1d"
-                ).unwrap(),
+                hash: GENESIS_HASH.to_vec(),
             };
 
             assert!(genesis.hash == genesis.hash_header());
@@ -109,7 +237,12 @@ impl Blockchain {
         }
 
         self.rebuild_utxos();
-        self.save_all();
+
+        // A loaded chain is already on disk; rewriting it would
+        // race a running node when the wallet CLI starts up
+        if created {
+            self.save_all();
+        }
     }
 
     /// ⛓ CONSENSUS v4 — ORIGINAL LOGIC (UNCHANGED)
@@ -119,27 +252,30 @@ impl Blockchain {
             return false;
         }
 
-        // Timestamp rules
-        if !self.blocks.is_empty() {
-            let mtp = median_time_past(&self.blocks);
-            if block.header.timestamp <= mtp {
-                return false;
-            }
-
-            if block.header.timestamp >
-                OffsetDateTime::now_utc().unix_timestamp() + MAX_FUTURE_DRIFT
-            {
-                return false;
-            }
+        // Unknown parent: nothing to connect to
+        let parent_len = match self
+            .blocks
+            .iter()
+            .position(|b| b.hash == block.header.prev_hash)
+        {
+            Some(i) => i + 1,
+            None => return false,
+        };
+
+        // A side branch is checked against its own history, and
+        // only worth its state replay if it wins
+        if parent_len != self.blocks.len() {
+            return self.connect_branch(vec![block]).is_ok();
         }
 
-        // Difficulty check
-        if block.header.target != calculate_next_target(&self.blocks) {
+        // Height must match the position on the chain, or the
+        // header would select the wrong consensus rules
+        if block.header.height != self.blocks.len() as u64 {
             return false;
         }
 
-        // PoW check
-        if !block.verify_pow() {
+        // Version, timestamp, difficulty, PoW
+        if !check_header(&self.blocks, &block.header, &block.hash) {
             return false;
         }
 
@@ -148,9 +284,18 @@ impl Blockchain {
             return false;
         }
 
+        // Transactions (version rules, lock time, spends)
+        // are checked against the state at the block's parent.
+        if !validate_block_transactions(&block, &self.utxos, median_time_past(&self.blocks)) {
+            return false;
+        }
+
         // Accept block
         self.blocks.push(block);
 
+        // Active chain before this block, for notifications
+        let mut previous = Vec::new();
+
         // Fork choice (v4+)
         if let Some(best_hash) = fork_choice::best_tip(&self.blocks) {
             let mut chain = Vec::new();
@@ -165,44 +310,132 @@ impl Blockchain {
             }
 
             chain.reverse();
-            self.blocks = chain;
+            previous = std::mem::replace(&mut self.blocks, chain);
+            previous.pop();
         }
 
         self.rebuild_utxos();
         self.save_all();
+
+        let events = self.branch_changes(&previous);
+        self.notify(events);
         true
     }
 
-    pub fn rebuild_utxos(&mut self) {
-        self.utxos.clear();
+    /// Switch to a branch forking off the active chain
+    ///
+    /// `branch` starts at a child of an active-chain block and
+    /// is validated block by block against its own history.
+    /// Adopted only if it ends with more cumulative work than
+    /// the active chain; otherwise nothing changes.
+    ///
+    /// Headers and work are checked first, so a branch that
+    /// cannot win never costs a replay of the fork-point state.
+    /// The error tells an invalid block apart from a branch that
+    /// is merely not better.
+    pub fn connect_branch(&mut self, branch: Vec<Block>) -> Result<(), BranchError> {
+        let fork = match branch
+            .first()
+            .and_then(|b| self.blocks.iter().position(|x| x.hash == b.header.prev_hash))
+        {
+            Some(i) => i + 1,
+            None => return Err(BranchError::UnknownParent),
+        };
+
+        let mut candidate = self.blocks[..fork].to_vec();
+
+        for (index, block) in branch.into_iter().enumerate() {
+            let parent = &candidate[candidate.len() - 1];
+            if block.header.height != candidate.len() as u64
+                || block.header.prev_hash != parent.hash
+            {
+                return Err(BranchError::Invalid(index));
+            }
 
-        for block in &self.blocks {
-            for (tx_index, tx) in block.transactions.iter().enumerate() {
-                let txid = hex::encode(tx.txid());
-
-                for input in &tx.inputs {
-                    self.utxos.remove(&format!(
-                        "{}:{}",
-                        hex::encode(&input.txid),
-                        input.index
-                    ));
-                }
+            if !check_header(&candidate, &block.header, &block.hash)
+                || merkle_root(&block.transactions) != block.header.merkle_root
+            {
+                return Err(BranchError::Invalid(index));
+            }
+            candidate.push(block);
+        }
 
+        if cumulative_work(&candidate) <= cumulative_work(&self.blocks) {
+            return Err(BranchError::InsufficientWork);
+        }
+
+        let mut utxos = if fork == self.blocks.len() {
+            self.utxos.clone()
+        } else {
+            utxos_for(&self.blocks[..fork])
+        };
+
+        for (height, block) in candidate.iter().enumerate().skip(fork) {
+            if !validate_block_transactions(block, &utxos, median_time_past(&candidate[..height])) {
+                return Err(BranchError::Invalid(height - fork));
+            }
+
+            for (tx_index, tx) in block.transactions.iter().enumerate() {
                 let is_coinbase = tx_index == 0 && tx.inputs.is_empty();
+                apply_transaction(&mut utxos, tx, block.header.height, is_coinbase);
+            }
+        }
 
-                for (i, o) in tx.outputs.iter().enumerate() {
-                    self.utxos.insert(
-                        format!("{}:{}", txid, i),
-                        UTXO {
-                            value: o.value,
-                            pubkey_hash: o.pubkey_hash.clone(),
-                            height: block.header.height,
-                            is_coinbase,
-                        },
-                    );
-                }
+        let previous = std::mem::replace(&mut self.blocks, candidate);
+        self.utxos = utxos;
+        self.save_all();
+
+        let events = self.branch_changes(&previous);
+        self.notify(events);
+        Ok(())
+    }
+
+    /// Active-chain headers following the first locator hash we
+    /// share, up to `stop` (inclusive) or `max` headers
+    pub fn headers_after(
+        &self,
+        locator: &[Vec<u8>],
+        stop: Option<&[u8]>,
+        max: usize,
+    ) -> Vec<BlockHeader> {
+        // Genesis is always shared
+        let start = locator
+            .iter()
+            .find_map(|h| self.blocks.iter().position(|b| &b.hash == h))
+            .unwrap_or(0);
+
+        let mut headers = Vec::new();
+        for block in self.blocks.iter().skip(start + 1).take(max) {
+            headers.push(block.header.clone());
+            if Some(block.hash.as_slice()) == stop {
+                break;
             }
         }
+        headers
+    }
+
+    /// Events turning `previous` into the current active chain
+    fn branch_changes(&self, previous: &[Block]) -> Vec<ChainEvent> {
+        let current: HashSet<&Vec<u8>> = self.blocks.iter().map(|b| &b.hash).collect();
+        let before: HashSet<&Vec<u8>> = previous.iter().map(|b| &b.hash).collect();
+
+        let disconnected = previous
+            .iter()
+            .rev()
+            .filter(|b| !current.contains(&b.hash))
+            .map(|b| ChainEvent::BlockDisconnected(b.clone()));
+
+        let connected = self
+            .blocks
+            .iter()
+            .filter(|b| !before.contains(&b.hash))
+            .map(|b| ChainEvent::BlockConnected(b.clone()));
+
+        disconnected.chain(connected).collect()
+    }
+
+    pub fn rebuild_utxos(&mut self) {
+        self.utxos = utxos_for(&self.blocks);
     }
 
     pub fn save_all(&self) {
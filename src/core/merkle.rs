@@ -21,4 +21,52 @@ pub fn merkle_root(txs: &[Transaction]) -> Vec<u8> {
     }
 
     hashes[0].clone()
-}
\ No newline at end of file
+}
+
+/// Sibling hashes from leaf `index` up to the root
+///
+/// Follows merkle_root exactly (odd levels duplicate
+/// their last hash).
+pub fn merkle_branch(txs: &[Transaction], index: usize) -> Vec<Vec<u8>> {
+    let mut branch = Vec::new();
+    if index >= txs.len() {
+        return branch;
+    }
+
+    let mut hashes: Vec<Vec<u8>> =
+        txs.iter().map(|t| t.txid()).collect();
+    let mut pos = index;
+
+    while hashes.len() > 1 {
+        if hashes.len() % 2 == 1 {
+            hashes.push(hashes.last().unwrap().clone());
+        }
+
+        branch.push(hashes[pos ^ 1].clone());
+
+        hashes = hashes
+            .chunks(2)
+            .map(|pair| sha256(&[pair[0].clone(), pair[1].clone()].concat()))
+            .collect();
+        pos /= 2;
+    }
+
+    branch
+}
+
+/// Recompute the root from a leaf and its branch
+pub fn root_from_branch(txid: &[u8], index: usize, branch: &[Vec<u8>]) -> Vec<u8> {
+    let mut hash = txid.to_vec();
+    let mut pos = index;
+
+    for sibling in branch {
+        hash = if pos & 1 == 0 {
+            sha256(&[hash, sibling.clone()].concat())
+        } else {
+            sha256(&[sibling.clone(), hash].concat())
+        };
+        pos /= 2;
+    }
+
+    hash
+}
@@ -32,21 +32,178 @@ pub struct TxInput {
 
     // Wallet metadata (non-consensus but serialized)
     pub address_index: u32,
+
+    // Multisig spends (v6): one entry per signing key.
+    // NOT part of txid or sighash.
+    #[serde(default)]
+    pub signatures: Vec<InputSignature>,
+
+    // HTLC claims (v6): revealed SHA-256 preimage.
+    // NOT part of txid or sighash.
+    #[serde(default)]
+    pub preimage: Vec<u8>,
+}
+
+/// One key's signature on a multisig input
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputSignature {
+    pub pubkey: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+/// Maximum keys in an m-of-n output (CONSENSUS v6)
+pub const MAX_MULTISIG_KEYS: usize = 16;
+
+/// HTLC claims reveal exactly this many bytes (CONSENSUS v6)
+pub const HTLC_PREIMAGE_SIZE: usize = 32;
+
+/// Spending condition of an output
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputKind {
+    /// Single key: spender reveals a pubkey hashing to `pubkey_hash`
+    #[default]
+    PubkeyHash,
+
+    /// m-of-n (CONSENSUS v6): `required` signatures from
+    /// distinct keys whose hashes are in `pubkey_hashes`.
+    /// `pubkey_hash` of the output is unused (empty).
+    Multisig {
+        required: u32,
+        pubkey_hashes: Vec<Vec<u8>>,
+    },
+
+    /// Hash-time-locked (CONSENSUS v6), for atomic swaps:
+    /// - claim:  `claim_pubkey_hash` key + preimage with
+    ///   SHA256(preimage) == `payment_hash`
+    /// - refund: `refund_pubkey_hash` key, at height >= `timeout_height`
+    ///
+    /// `pubkey_hash` of the output is unused (empty).
+    Htlc {
+        payment_hash: Vec<u8>,
+        claim_pubkey_hash: Vec<u8>,
+        refund_pubkey_hash: Vec<u8>,
+        timeout_height: u64,
+    },
+
+    /// Provably unspendable data carrier (CONSENSUS v6)
+    ///
+    /// Never enters the UTXO set. Size and value are
+    /// limited by policy, not consensus.
+    DataCarrier {
+        data: Vec<u8>,
+    },
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TxOutput {
     pub value: u64,
     pub pubkey_hash: Vec<u8>,
+
+    // Legacy transactions may only use PubkeyHash
+    #[serde(default)]
+    pub kind: OutputKind,
+}
+
+impl TxOutput {
+    /// Output locked to a single pubkey hash
+    pub fn pay_to_pubkey_hash(value: u64, pubkey_hash: Vec<u8>) -> Self {
+        Self {
+            value,
+            pubkey_hash,
+            kind: OutputKind::PubkeyHash,
+        }
+    }
+
+    /// m-of-n multisig output (CONSENSUS v6)
+    pub fn multisig(value: u64, required: u32, pubkey_hashes: Vec<Vec<u8>>) -> Self {
+        Self {
+            value,
+            pubkey_hash: Vec::new(),
+            kind: OutputKind::Multisig {
+                required,
+                pubkey_hashes,
+            },
+        }
+    }
+
+    /// Hash-time-locked output (CONSENSUS v6)
+    pub fn htlc(
+        value: u64,
+        payment_hash: Vec<u8>,
+        claim_pubkey_hash: Vec<u8>,
+        refund_pubkey_hash: Vec<u8>,
+        timeout_height: u64,
+    ) -> Self {
+        Self {
+            value,
+            pubkey_hash: Vec::new(),
+            kind: OutputKind::Htlc {
+                payment_hash,
+                claim_pubkey_hash,
+                refund_pubkey_hash,
+                timeout_height,
+            },
+        }
+    }
+
+    /// Zero-value data-carrier output (CONSENSUS v6)
+    pub fn data_carrier(data: Vec<u8>) -> Self {
+        Self {
+            value: 0,
+            pubkey_hash: Vec::new(),
+            kind: OutputKind::DataCarrier { data },
+        }
+    }
+
+    /// Outputs that can never be spent
+    pub fn is_unspendable(&self) -> bool {
+        matches!(self.kind, OutputKind::DataCarrier { .. })
+    }
+}
+
+/// Legacy transaction format (v4 / v5)
+pub const TX_VERSION_LEGACY: u32 = 1;
+
+/// Lock-time transaction format (CONSENSUS v6)
+///
+/// - lock_time is committed to by txid and sighash
+/// - sighash excludes input pubkeys & signatures
+/// - sighash commits to GENESIS_HASH and FORK_ID
+/// - outputs may use any OutputKind
+pub const TX_VERSION_LOCKTIME: u32 = 2;
+
+/// lock_time below this is a block height,
+/// at or above it a unix timestamp (compared to MTP)
+pub const LOCKTIME_THRESHOLD: u64 = 500_000_000;
+
+fn legacy_version() -> u32 {
+    TX_VERSION_LEGACY
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Transaction {
+    #[serde(default = "legacy_version")]
+    pub version: u32,
+
     pub inputs: Vec<TxInput>,
     pub outputs: Vec<TxOutput>,
+
+    /// Earliest height / MTP this tx may be mined at (0 = none)
+    #[serde(default)]
+    pub lock_time: u64,
 }
 
 impl Transaction {
+    /// Legacy-format transaction (no lock time)
+    pub fn new(inputs: Vec<TxInput>, outputs: Vec<TxOutput>) -> Self {
+        Self {
+            version: TX_VERSION_LEGACY,
+            inputs,
+            outputs,
+            lock_time: 0,
+        }
+    }
+
     /// Transaction ID (CONSENSUS v5)
     ///
     /// Used for:
@@ -72,6 +229,7 @@ impl Transaction {
 
     /// Estimated serialized size (POLICY ONLY)
     pub fn serialized_size(&self) -> usize {
-        self.inputs.len() * 148 + self.outputs.len() * 34 + 10
+        let extra_sigs: usize = self.inputs.iter().map(|i| i.signatures.len()).sum();
+        self.inputs.len() * 148 + extra_sigs * 100 + self.outputs.len() * 34 + 10
     }
 }
@@ -1,12 +1,55 @@
 use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
 
+use crate::core::transaction::{Transaction, OutputKind};
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct UTXO {
     pub value: u64,
     pub pubkey_hash: Vec<u8>,
     pub height: u64,      // block height where this UTXO was created
     pub is_coinbase: bool,
+
+    #[serde(default)]
+    pub kind: OutputKind,
 }
 
 pub type UTXOSet = HashMap<String, UTXO>;
+
+/// UTXO set key for an outpoint ("txid_hex:index")
+pub fn outpoint_key(txid: &[u8], index: u32) -> String {
+    format!("{}:{}", hex::encode(txid), index)
+}
+
+/// Spend a transaction's inputs and add its outputs
+///
+/// Unspendable (data-carrier) outputs are never added.
+pub fn apply_transaction(
+    utxos: &mut UTXOSet,
+    tx: &Transaction,
+    height: u64,
+    is_coinbase: bool,
+) {
+    let txid = tx.txid();
+
+    for input in &tx.inputs {
+        utxos.remove(&outpoint_key(&input.txid, input.index));
+    }
+
+    for (i, o) in tx.outputs.iter().enumerate() {
+        if o.is_unspendable() {
+            continue;
+        }
+
+        utxos.insert(
+            outpoint_key(&txid, i as u32),
+            UTXO {
+                value: o.value,
+                pubkey_hash: o.pubkey_hash.clone(),
+                height,
+                is_coinbase,
+                kind: o.kind.clone(),
+            },
+        );
+    }
+}
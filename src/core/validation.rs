@@ -2,19 +2,31 @@ use std::collections::HashSet;
 
 use secp256k1::PublicKey;
 
-use crate::core::transaction::Transaction;
-use crate::core::utxo::UTXOSet;
-use crate::crypto::{verify_signature, pubkey_hash};
+use crate::core::block::Block;
+use crate::core::transaction::{
+    Transaction, TxInput, OutputKind,
+    TX_VERSION_LEGACY, TX_VERSION_LOCKTIME, LOCKTIME_THRESHOLD, MAX_MULTISIG_KEYS,
+    HTLC_PREIMAGE_SIZE,
+};
+use crate::core::utxo::{UTXOSet, apply_transaction};
+use crate::crypto::{sha256, verify_signature, pubkey_hash, is_low_s, is_compressed_pubkey};
+use crate::consensus::version::{consensus_version_for_height, ConsensusVersion};
 
 const COINBASE_MATURITY: u64 = 100;
 
-/// ⚠️ CONSENSUS — v4 / v5
+/// ⚠️ CONSENSUS — v4 / v5 / v6
 /// Transaction validation rules
 pub fn validate_transaction(
     tx: &Transaction,
     utxos: &UTXOSet,
     current_height: u64,
 ) -> bool {
+    let version = consensus_version_for_height(current_height);
+
+    if !is_allowed_tx_version(tx, version) {
+        return false;
+    }
+
     // Coinbase tx
     if tx.inputs.is_empty() {
         return true;
@@ -44,35 +56,428 @@ pub fn validate_transaction(
         };
 
         // Coinbase maturity rule
-        if utxo.is_coinbase {
-            if current_height < utxo.height + COINBASE_MATURITY {
-                return false;
+        if utxo.is_coinbase && current_height < utxo.height + COINBASE_MATURITY {
+            return false;
+        }
+
+        let authorized = match &utxo.kind {
+            OutputKind::PubkeyHash => verify_key_signature(
+                &input.pubkey,
+                &input.signature,
+                &utxo.pubkey_hash,
+                &sighash,
+                version,
+            ),
+            OutputKind::Multisig { required, pubkey_hashes } => verify_multisig(
+                input,
+                *required,
+                pubkey_hashes,
+                &sighash,
+                version,
+            ),
+            OutputKind::Htlc {
+                payment_hash,
+                claim_pubkey_hash,
+                refund_pubkey_hash,
+                timeout_height,
+            } => {
+                version >= ConsensusVersion::V6
+                    && if input.preimage.is_empty() {
+                        // Refund path
+                        current_height >= *timeout_height
+                            && verify_key_signature(
+                                &input.pubkey,
+                                &input.signature,
+                                refund_pubkey_hash,
+                                &sighash,
+                                version,
+                            )
+                    } else {
+                        // Claim path
+                        input.preimage.len() == HTLC_PREIMAGE_SIZE
+                            && sha256(&input.preimage) == *payment_hash
+                            && verify_key_signature(
+                                &input.pubkey,
+                                &input.signature,
+                                claim_pubkey_hash,
+                                &sighash,
+                                version,
+                            )
+                    }
             }
+            // Never in the UTXO set; rejected defensively
+            OutputKind::DataCarrier { .. } => false,
+        };
+
+        if !authorized {
+            return false;
         }
 
-        let pubkey = match PublicKey::from_slice(&input.pubkey) {
-            Ok(pk) => pk,
+        input_sum = input_sum.saturating_add(utxo.value);
+    }
+
+    for output in &tx.outputs {
+        output_sum = output_sum.saturating_add(output.value);
+    }
+
+    input_sum >= output_sum
+}
+
+/// Single-key authorization: pubkey hashes to `expected_hash`
+/// and signs `sighash`
+fn verify_key_signature(
+    pubkey_bytes: &[u8],
+    signature: &[u8],
+    expected_hash: &[u8],
+    sighash: &[u8],
+    version: ConsensusVersion,
+) -> bool {
+    // Canonical encodings (v6+)
+    if version >= ConsensusVersion::V6
+        && (!is_compressed_pubkey(pubkey_bytes) || !is_low_s(signature))
+    {
+        return false;
+    }
+
+    let pubkey = match PublicKey::from_slice(pubkey_bytes) {
+        Ok(pk) => pk,
+        Err(_) => return false,
+    };
+
+    if pubkey_hash(&pubkey) != expected_hash {
+        return false;
+    }
+
+    verify_signature(
+        sighash,
+        signature,
+        &pubkey.serialize(),
+    )
+}
+
+/// ⚠️ CONSENSUS — v6
+/// m-of-n authorization
+///
+/// - at least `required` signatures
+/// - every signature valid, each from a distinct listed key
+fn verify_multisig(
+    input: &TxInput,
+    required: u32,
+    pubkey_hashes: &[Vec<u8>],
+    sighash: &[u8],
+    version: ConsensusVersion,
+) -> bool {
+    if version < ConsensusVersion::V6 {
+        return false;
+    }
+
+    if input.signatures.len() < required as usize
+        || input.signatures.len() > pubkey_hashes.len()
+    {
+        return false;
+    }
+
+    let mut used = HashSet::new();
+
+    for s in &input.signatures {
+        let hash = match PublicKey::from_slice(&s.pubkey) {
+            Ok(pk) => pubkey_hash(&pk),
             Err(_) => return false,
         };
 
-        if pubkey_hash(&pubkey) != utxo.pubkey_hash {
+        if !pubkey_hashes.contains(&hash) || !used.insert(hash.clone()) {
             return false;
         }
 
-        if !verify_signature(
-            &sighash,
-            &input.signature,
-            &pubkey.serialize(),
-        ) {
+        if !verify_key_signature(&s.pubkey, &s.signature, &hash, sighash, version) {
             return false;
         }
+    }
 
-        input_sum = input_sum.saturating_add(utxo.value);
+    true
+}
+
+/// Output kinds must be well-formed (m-of-n bounds, 32-byte hashes,
+/// non-zero HTLC timeout)
+fn is_valid_output_kind(kind: &OutputKind) -> bool {
+    match kind {
+        OutputKind::PubkeyHash => true,
+        OutputKind::Multisig { required, pubkey_hashes } => {
+            *required >= 1
+                && (*required as usize) <= pubkey_hashes.len()
+                && pubkey_hashes.len() <= MAX_MULTISIG_KEYS
+                && pubkey_hashes.iter().all(|h| h.len() == 32)
+        }
+        OutputKind::Htlc {
+            payment_hash,
+            claim_pubkey_hash,
+            refund_pubkey_hash,
+            timeout_height,
+        } => {
+            payment_hash.len() == 32
+                && claim_pubkey_hash.len() == 32
+                && refund_pubkey_hash.len() == 32
+                && *timeout_height > 0
+        }
+        OutputKind::DataCarrier { .. } => true,
     }
+}
 
-    for output in &tx.outputs {
-        output_sum = output_sum.saturating_add(output.value);
+/// Transaction format versions allowed at a consensus version
+///
+/// v4 / v5: legacy only
+/// v6:      legacy or lock-time
+///
+/// Legacy serialization does not commit to output kinds,
+/// multisig / HTLC witnesses or lock_time, so legacy
+/// transactions may not use them.
+fn is_allowed_tx_version(tx: &Transaction, version: ConsensusVersion) -> bool {
+    match tx.version {
+        TX_VERSION_LEGACY => {
+            tx.lock_time == 0
+                && tx.outputs.iter().all(|o| o.kind == OutputKind::PubkeyHash)
+                && tx.inputs.iter().all(|i| i.signatures.is_empty() && i.preimage.is_empty())
+        }
+        TX_VERSION_LOCKTIME => {
+            version >= ConsensusVersion::V6
+                && tx.outputs.iter().all(|o| is_valid_output_kind(&o.kind))
+        }
+        _ => false,
     }
+}
 
-    input_sum >= output_sum
+/// ⚠️ CONSENSUS — v6
+/// Lock-time finality
+///
+/// A transaction may be mined in a block at `height`
+/// whose parent chain has `median_time_past` when:
+/// - lock_time == 0, or
+/// - lock_time <  LOCKTIME_THRESHOLD and lock_time <= height, or
+/// - lock_time >= LOCKTIME_THRESHOLD and lock_time <= median_time_past
+pub fn is_final_transaction(
+    tx: &Transaction,
+    height: u64,
+    median_time_past: i64,
+) -> bool {
+    if tx.lock_time == 0 {
+        return true;
+    }
+
+    if tx.lock_time < LOCKTIME_THRESHOLD {
+        tx.lock_time <= height
+    } else {
+        median_time_past >= 0 && tx.lock_time <= median_time_past as u64
+    }
+}
+
+/// ⚠️ CONSENSUS — v4 / v5 / v6
+/// Block transaction rules
+///
+/// v4 / v5: legacy transaction format only (no output kinds)
+/// v6:      every transaction final, every spend valid
+///          against the parent UTXO set plus earlier
+///          transactions in the same block
+pub fn validate_block_transactions(
+    block: &Block,
+    parent_utxos: &UTXOSet,
+    parent_median_time_past: i64,
+) -> bool {
+    let height = block.header.height;
+    let version = consensus_version_for_height(height);
+
+    if version < ConsensusVersion::V6 {
+        return block
+            .transactions
+            .iter()
+            .all(|tx| tx.version == TX_VERSION_LEGACY && is_allowed_tx_version(tx, version));
+    }
+
+    let mut view = parent_utxos.clone();
+
+    for (tx_index, tx) in block.transactions.iter().enumerate() {
+        if !is_final_transaction(tx, height, parent_median_time_past) {
+            return false;
+        }
+
+        let is_coinbase = tx_index == 0 && tx.inputs.is_empty();
+
+        if !is_coinbase && (tx.inputs.is_empty() || !validate_transaction(tx, &view, height)) {
+            return false;
+        }
+
+        apply_transaction(&mut view, tx, height, is_coinbase);
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::SecretKey;
+
+    use crate::core::block::BlockHeader;
+    use crate::core::transaction::{InputSignature, TxOutput};
+    use crate::core::utxo::{outpoint_key, UTXO};
+    use crate::consensus::params::MAX_TARGET;
+    use crate::consensus::version::CONSENSUS_V6_HEIGHT;
+    use crate::crypto::{public_key, secret_key_from_seed, sign};
+
+    const HEIGHT: u64 = CONSENSUS_V6_HEIGHT;
+
+    fn key(n: u8) -> SecretKey {
+        secret_key_from_seed(&[n; 32])
+    }
+
+    fn hash_of(sk: &SecretKey) -> Vec<u8> {
+        pubkey_hash(&public_key(sk))
+    }
+
+    /// One confirmed output `[1; 32]:0` of `kind`
+    fn utxos(pubkey_hash: Vec<u8>, kind: OutputKind) -> UTXOSet {
+        [(outpoint_key(&[1u8; 32], 0), UTXO {
+            value: 10_000,
+            pubkey_hash,
+            height: 1,
+            is_coinbase: false,
+            kind,
+        })]
+        .into_iter()
+        .collect()
+    }
+
+    /// Unsigned spend of `[1; 32]:0`
+    fn unsigned(version: u32, lock_time: u64) -> Transaction {
+        Transaction {
+            version,
+            inputs: vec![TxInput {
+                txid: vec![1u8; 32],
+                index: 0,
+                pubkey: Vec::new(),
+                signature: Vec::new(),
+                address_index: 0,
+                signatures: Vec::new(),
+                preimage: Vec::new(),
+            }],
+            outputs: vec![TxOutput::pay_to_pubkey_hash(9_000, vec![5u8; 32])],
+            lock_time,
+        }
+    }
+
+    fn signed(sk: &SecretKey) -> Transaction {
+        let mut tx = unsigned(TX_VERSION_LOCKTIME, 0);
+        let sighash = tx.sighash();
+        tx.inputs[0].pubkey = public_key(sk).serialize().to_vec();
+        tx.inputs[0].signature = sign(&sighash, sk);
+        tx
+    }
+
+    fn cosigned(signers: &[&SecretKey]) -> Transaction {
+        let mut tx = unsigned(TX_VERSION_LOCKTIME, 0);
+        let sighash = tx.sighash();
+        tx.inputs[0].signatures = signers
+            .iter()
+            .map(|sk| InputSignature {
+                pubkey: public_key(sk).serialize().to_vec(),
+                signature: sign(&sighash, sk),
+            })
+            .collect();
+        tx
+    }
+
+    #[test]
+    fn v6_rejects_uncompressed_pubkey() {
+        let sk = key(1);
+        let uncompressed = public_key(&sk).serialize_uncompressed().to_vec();
+        let set = utxos(sha256(&uncompressed), OutputKind::PubkeyHash);
+
+        let mut tx = signed(&sk);
+        tx.inputs[0].pubkey = uncompressed;
+
+        assert!(!validate_transaction(&tx, &set, HEIGHT));
+    }
+
+    #[test]
+    fn v6_rejects_wrong_key() {
+        let set = utxos(hash_of(&key(1)), OutputKind::PubkeyHash);
+        assert!(!validate_transaction(&signed(&key(2)), &set, HEIGHT));
+    }
+
+    #[test]
+    fn v2_spend_valid_from_v6() {
+        let sk = key(1);
+        let set = utxos(hash_of(&sk), OutputKind::PubkeyHash);
+        let tx = signed(&sk);
+
+        assert!(validate_transaction(&tx, &set, HEIGHT));
+        assert!(!validate_transaction(&tx, &set, HEIGHT - 1));
+    }
+
+    #[test]
+    fn lock_time_finality() {
+        let by_height = unsigned(TX_VERSION_LOCKTIME, 500);
+        assert!(!is_final_transaction(&by_height, 499, 0));
+        assert!(is_final_transaction(&by_height, 500, 0));
+
+        let by_time = unsigned(TX_VERSION_LOCKTIME, LOCKTIME_THRESHOLD + 100);
+        assert!(!is_final_transaction(&by_time, HEIGHT, (LOCKTIME_THRESHOLD + 99) as i64));
+        assert!(is_final_transaction(&by_time, HEIGHT, (LOCKTIME_THRESHOLD + 100) as i64));
+    }
+
+    #[test]
+    fn block_rejects_non_final_transaction() {
+        let coinbase = Transaction::new(
+            Vec::new(),
+            vec![TxOutput::pay_to_pubkey_hash(50, vec![5u8; 32])],
+        );
+        let block = |tx: Transaction| Block {
+            header: BlockHeader {
+                version: 0,
+                height: HEIGHT,
+                timestamp: 0,
+                prev_hash: Vec::new(),
+                nonce: 0,
+                target: MAX_TARGET,
+                merkle_root: Vec::new(),
+            },
+            transactions: vec![coinbase.clone(), tx],
+            hash: Vec::new(),
+        };
+
+        let sk = key(1);
+        let set = utxos(hash_of(&sk), OutputKind::PubkeyHash);
+        assert!(validate_block_transactions(&block(signed(&sk)), &set, 0));
+
+        let mut late = unsigned(TX_VERSION_LOCKTIME, HEIGHT + 1);
+        let sighash = late.sighash();
+        late.inputs[0].pubkey = public_key(&sk).serialize().to_vec();
+        late.inputs[0].signature = sign(&sighash, &sk);
+        assert!(!validate_block_transactions(&block(late), &set, 0));
+    }
+
+    #[test]
+    fn multisig_threshold() {
+        let (a, b, c) = (key(1), key(2), key(3));
+        let kind = OutputKind::Multisig {
+            required: 2,
+            pubkey_hashes: vec![hash_of(&a), hash_of(&b), hash_of(&c)],
+        };
+        let set = utxos(Vec::new(), kind);
+
+        assert!(validate_transaction(&cosigned(&[&a, &c]), &set, HEIGHT));
+        assert!(!validate_transaction(&cosigned(&[&a]), &set, HEIGHT));
+        assert!(!validate_transaction(&cosigned(&[&a, &a]), &set, HEIGHT));
+        assert!(!validate_transaction(&cosigned(&[&a, &key(4)]), &set, HEIGHT));
+    }
+
+    #[test]
+    fn legacy_transactions_may_not_set_lock_time() {
+        let legacy = unsigned(TX_VERSION_LEGACY, 0);
+        assert!(is_allowed_tx_version(&legacy, ConsensusVersion::V6));
+
+        // Not committed to by the legacy txid or sighash
+        let locked = unsigned(TX_VERSION_LEGACY, 500);
+        assert!(!is_allowed_tx_version(&locked, ConsensusVersion::V6));
+        assert!(!is_allowed_tx_version(&locked, ConsensusVersion::V5));
+    }
 }
@@ -1,4 +1,5 @@
 pub mod signature;
+pub mod sigcache;
 
 pub use signature::{
     sha256,
@@ -8,4 +9,6 @@ pub use signature::{
     pubkey_hash_from_bytes,
     sign,
     verify_signature,
+    is_low_s,
+    is_compressed_pubkey,
 };
@@ -0,0 +1,195 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Mutex, OnceLock};
+
+use serde::Serialize;
+use sha2::{Sha256, Digest};
+
+/// Default cache capacity (POLICY ONLY)
+pub const DEFAULT_SIGCACHE_ENTRIES: usize = 100_000;
+
+/// Signature verification cache (NON-CONSENSUS)
+///
+/// Remembers (sighash, pubkey, signature) triples that already
+/// verified, so a transaction checked on mempool entry is not
+/// re-verified by the miner or when its block is connected.
+///
+/// Only successful verifications are stored. A miss always
+/// falls through to secp256k1, so the cache can never make
+/// an invalid signature pass.
+pub struct SignatureCache {
+    entries: HashSet<[u8; 32]>,
+    order: VecDeque<[u8; 32]>,
+    max_entries: usize,
+    hits: u64,
+    misses: u64,
+}
+
+/// Cache counters exposed to the API
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SigCacheStats {
+    pub entries: usize,
+    pub max_entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl SignatureCache {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: HashSet::new(),
+            order: VecDeque::new(),
+            max_entries,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns true if this triple already verified
+    pub fn contains(&mut self, key: &[u8; 32]) -> bool {
+        if self.entries.contains(key) {
+            self.hits += 1;
+            true
+        } else {
+            self.misses += 1;
+            false
+        }
+    }
+
+    /// Cached result for `key`, otherwise run `verify` and
+    /// remember it if it succeeds
+    ///
+    /// Lookup and insert happen under one borrow, so callers
+    /// sharing the global cache take its lock once per check.
+    pub fn check_or_verify(&mut self, key: [u8; 32], verify: impl FnOnce() -> bool) -> bool {
+        if self.contains(&key) {
+            return true;
+        }
+
+        if !verify() {
+            return false;
+        }
+
+        self.insert(key);
+        true
+    }
+
+    /// Record a successful verification (oldest entry evicted first)
+    pub fn insert(&mut self, key: [u8; 32]) {
+        if self.max_entries == 0 || !self.entries.insert(key) {
+            return;
+        }
+
+        self.order.push_back(key);
+        self.evict();
+    }
+
+    /// Change the size limit, evicting if needed
+    pub fn set_max_entries(&mut self, max_entries: usize) {
+        self.max_entries = max_entries;
+        self.evict();
+    }
+
+    pub fn stats(&self) -> SigCacheStats {
+        SigCacheStats {
+            entries: self.entries.len(),
+            max_entries: self.max_entries,
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.entries.len() > self.max_entries {
+            match self.order.pop_front() {
+                Some(old) => {
+                    self.entries.remove(&old);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+/// Cache key = SHA256(len-prefixed msg | pubkey | signature)
+pub fn cache_key(msg: &[u8], sig_bytes: &[u8], pubkey_bytes: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in [msg, pubkey_bytes, sig_bytes] {
+        hasher.update((part.len() as u32).to_le_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// Process-wide cache shared by mempool, miner and block validation
+pub fn global() -> &'static Mutex<SignatureCache> {
+    static CACHE: OnceLock<Mutex<SignatureCache>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(SignatureCache::new(DEFAULT_SIGCACHE_ENTRIES)))
+}
+
+/// Snapshot of the global cache counters
+pub fn stats() -> SigCacheStats {
+    global().lock().unwrap().stats()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_after_successful_verification() {
+        let mut cache = SignatureCache::new(10);
+        let key = cache_key(b"msg", b"sig", b"pubkey");
+
+        let mut runs = 0;
+        assert!(cache.check_or_verify(key, || { runs += 1; true }));
+        assert!(cache.check_or_verify(key, || { runs += 1; true }));
+        assert_eq!(runs, 1);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+    }
+
+    #[test]
+    fn failures_are_not_cached() {
+        let mut cache = SignatureCache::new(10);
+        let key = cache_key(b"msg", b"sig", b"pubkey");
+
+        assert!(!cache.check_or_verify(key, || false));
+        assert!(!cache.contains(&key));
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn evicts_oldest_at_capacity() {
+        let mut cache = SignatureCache::new(2);
+        let keys: Vec<[u8; 32]> = (0u8..3).map(|i| cache_key(&[i], b"sig", b"pubkey")).collect();
+
+        for key in &keys {
+            cache.insert(*key);
+        }
+        assert_eq!(cache.stats().entries, 2);
+        assert!(!cache.contains(&keys[0]));
+        assert!(cache.contains(&keys[1]));
+        assert!(cache.contains(&keys[2]));
+
+        cache.set_max_entries(1);
+        assert!(!cache.contains(&keys[1]));
+        assert!(cache.contains(&keys[2]));
+
+        cache.set_max_entries(0);
+        cache.insert(keys[0]);
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn key_covers_message_pubkey_and_signature() {
+        let key = cache_key(b"msg", b"sig", b"pubkey");
+
+        assert_ne!(key, cache_key(b"msh", b"sig", b"pubkey"));
+        assert_ne!(key, cache_key(b"msg", b"sih", b"pubkey"));
+        assert_ne!(key, cache_key(b"msg", b"sig", b"pubkez"));
+
+        // Length prefixes: moving bytes between parts changes the key
+        assert_ne!(key, cache_key(b"msgs", b"ig", b"pubkey"));
+    }
+}
@@ -4,6 +4,8 @@ use secp256k1::{
 };
 use sha2::{Sha256, Digest};
 
+use crate::crypto::sigcache;
+
 /// SHA256 helper
 pub fn sha256(data: &[u8]) -> Vec<u8> {
     Sha256::digest(data).to_vec()
@@ -37,16 +39,57 @@ pub fn sign(msg: &[u8], sk: &SecretKey) -> Vec<u8> {
     let hash = sha256(msg);
     let message = Message::from_digest_slice(&hash).expect("32 bytes");
 
-    secp.sign_ecdsa(&message, sk)
-        .serialize_compact()
-        .to_vec()
+    // Always emit the canonical low-S form (v6 rule)
+    let mut sig = secp.sign_ecdsa(&message, sk);
+    sig.normalize_s();
+
+    sig.serialize_compact().to_vec()
+}
+
+/// Canonical signature check (CONSENSUS v6)
+///
+/// Signature MUST be 64-byte compact with S in the lower half
+/// of the curve order. High-S twins are rejected.
+pub fn is_low_s(sig_bytes: &[u8]) -> bool {
+    let sig = match Signature::from_compact(sig_bytes) {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
+
+    let mut normalized = sig;
+    normalized.normalize_s();
+    normalized == sig
+}
+
+/// Canonical pubkey check (CONSENSUS v6)
+///
+/// Pubkey MUST be a valid 33-byte compressed encoding.
+pub fn is_compressed_pubkey(pubkey_bytes: &[u8]) -> bool {
+    pubkey_bytes.len() == 33
+        && (pubkey_bytes[0] == 0x02 || pubkey_bytes[0] == 0x03)
+        && PublicKey::from_slice(pubkey_bytes).is_ok()
 }
 
 /// verify signature using raw pubkey bytes (validation side)
+///
+/// Successful checks are remembered in the signature cache,
+/// so the same triple is only run through secp256k1 once.
 pub fn verify_signature(
     msg: &[u8],
     sig_bytes: &[u8],
     pubkey_bytes: &[u8],
+) -> bool {
+    let key = sigcache::cache_key(msg, sig_bytes, pubkey_bytes);
+    sigcache::global()
+        .lock()
+        .unwrap()
+        .check_or_verify(key, || verify_signature_uncached(msg, sig_bytes, pubkey_bytes))
+}
+
+fn verify_signature_uncached(
+    msg: &[u8],
+    sig_bytes: &[u8],
+    pubkey_bytes: &[u8],
 ) -> bool {
     let secp = Secp256k1::verification_only();
 
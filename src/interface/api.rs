@@ -1,44 +1,87 @@
 use tokio::net::TcpListener;
-use serde::Serialize;
+use serde::{Serialize, Deserialize};
 use std::sync::{Arc, Mutex};
 use std::net::SocketAddr;
 
 use axum::{
     Router,
     Json,
-    routing::get,
-    extract::State,
+    routing::{get, post},
+    extract::{State, Path, Query},
     http::StatusCode,
 };
 
-use crate::core::chain::Blockchain;
+use crate::consensus::deployment::{deployment_statuses, DeploymentStatus};
+use crate::core::chain::{Blockchain, median_time_past};
+use crate::core::transaction::Transaction;
+use crate::core::validation::validate_transaction;
+use crate::crypto::sigcache::{self, SigCacheStats};
+use crate::node::fee_estimator::{DEFAULT_CONFIRM_TARGET, MAX_CONFIRM_TARGET};
+use crate::node::mempool::Mempool;
+use crate::node::p2p::P2PNetwork;
+use crate::notary::{inclusion_proof, InclusionProof};
+use crate::wallet::{Wallet, DEFAULT_FEE_PER_BYTE};
 
 const COINBASE_MATURITY: u64 = 100;
 
 #[derive(Clone)]
 struct AppState {
     chain: Arc<Mutex<Blockchain>>,
+    mempool: Arc<Mutex<Mempool>>,
+    wallet: Arc<Mutex<Wallet>>,
+    p2p: Arc<P2PNetwork>,
 }
 
 /* ───────── API START ───────── */
 
-pub async fn start_api(chain: Arc<Mutex<Blockchain>>, port: u16) {
-    println!("🌐 API starting on http://127.0.0.1:{}/status", port);
-
-    let state = AppState { chain };
+/// Serve the read-only API on all interfaces (`port`) and the
+/// wallet-spending routes on loopback only (`wallet_port`)
+///
+/// Anything that signs with the node wallet must never be
+/// reachable from the network.
+pub async fn start_api(
+    chain: Arc<Mutex<Blockchain>>,
+    mempool: Arc<Mutex<Mempool>>,
+    wallet: Arc<Mutex<Wallet>>,
+    p2p: Arc<P2PNetwork>,
+    port: u16,
+    wallet_port: u16,
+) {
+    let state = AppState { chain, mempool, wallet, p2p };
 
-    let app = Router::new()
+    let public = Router::new()
         .route("/status", get(status))
+        .route("/sigcache", get(sigcache_stats))
+        .route("/deployments", get(deployments))
+        .route("/fee-estimate", get(fee_estimate))
+        .route("/proof/:txid", get(proof))
+        .route("/proof/verify", post(verify_proof))
+        .with_state(state.clone());
+
+    let local = Router::new()
+        .route("/notarize", post(notarize))
         .with_state(state);
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    let listener = TcpListener::bind(addr)
+    let public_addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let local_addr = SocketAddr::from(([127, 0, 0, 1], wallet_port));
+
+    println!("🌐 API listening on {}", public_addr);
+    println!("🔒 Wallet API listening on {} (local only)", local_addr);
+
+    let public_listener = TcpListener::bind(public_addr)
         .await
         .expect("API bind failed");
-
-    axum::serve(listener, app)
+    let local_listener = TcpListener::bind(local_addr)
         .await
-        .expect("API server crashed");
+        .expect("Wallet API bind failed");
+
+    let (public_result, local_result) = tokio::join!(
+        axum::serve(public_listener, public),
+        axum::serve(local_listener, local),
+    );
+
+    public_result.expect("API server crashed");
+    local_result.expect("Wallet API server crashed");
 }
 
 /* ───────── STATUS ───────── */
@@ -60,6 +103,11 @@ struct StatusResponse {
 async fn status(
     State(state): State<AppState>,
 ) -> Result<Json<StatusResponse>, StatusCode> {
+    let mempool_size = state
+        .mempool
+        .lock()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .size();
     let chain = state.chain.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     let height = chain.height();
 
@@ -92,10 +140,182 @@ async fn status(
         height,
         blocks: chain.blocks.len(),
         utxos: chain.utxos.len(),
-        mempool: chain.mempool.len(),
+        mempool: mempool_size,
         total_supply: total,
         spendable_supply: spendable,
         locked_supply: locked,
         next_spendable_height,
     }))
 }
+
+/* ───────── SIGNATURE CACHE ───────── */
+
+async fn sigcache_stats() -> Json<SigCacheStats> {
+    Json(sigcache::stats())
+}
+
+/* ───────── DEPLOYMENTS ───────── */
+
+/// Version-bits upgrade progress for the next block
+async fn deployments(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<DeploymentStatus>>, StatusCode> {
+    let chain = state.chain.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(deployment_statuses(&chain.blocks, chain.height())))
+}
+
+/* ───────── FEE ESTIMATION ───────── */
+
+#[derive(Deserialize)]
+struct FeeEstimateQuery {
+    blocks: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct FeeEstimateResponse {
+    blocks: usize,
+    /// None until enough confirmations have been observed
+    fee_per_byte: Option<u64>,
+}
+
+/// Fee rate to confirm within `?blocks=N` (default 6)
+async fn fee_estimate(
+    State(state): State<AppState>,
+    Query(query): Query<FeeEstimateQuery>,
+) -> Result<Json<FeeEstimateResponse>, StatusCode> {
+    let blocks = query.blocks.unwrap_or(DEFAULT_CONFIRM_TARGET);
+    if blocks == 0 || blocks > MAX_CONFIRM_TARGET {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mempool = state.mempool.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(FeeEstimateResponse {
+        blocks,
+        fee_per_byte: mempool.fee_estimator().estimate(blocks),
+    }))
+}
+
+/* ───────── NOTARIZATION ───────── */
+
+#[derive(Deserialize)]
+struct NotarizeRequest {
+    /// SHA-256 of the document, hex
+    document_hash: String,
+}
+
+#[derive(Serialize)]
+struct NotarizeResponse {
+    txid: String,
+    tx: Transaction,
+}
+
+/// Commit a document hash in a data-carrier transaction
+/// funded by the node wallet and queue it for mining
+async fn notarize(
+    State(state): State<AppState>,
+    Json(req): Json<NotarizeRequest>,
+) -> Result<Json<NotarizeResponse>, StatusCode> {
+    let document_hash = hex::decode(&req.document_hash)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    if document_hash.len() != 32 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut wallet = state.wallet.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut mempool = state.mempool.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let chain = state.chain.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let height = chain.height();
+
+    // For this transaction only; the shared wallet keeps its rate
+    let fee_per_byte = mempool
+        .fee_estimator()
+        .estimate(DEFAULT_CONFIRM_TARGET)
+        .unwrap_or(DEFAULT_FEE_PER_BYTE);
+
+    let tx = wallet
+        .create_data_transaction(&chain.utxos, document_hash, height, fee_per_byte)
+        .map_err(|_| StatusCode::UNPROCESSABLE_ENTITY)?;
+
+    if !validate_transaction(&tx, &chain.utxos, height) {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let mtp = median_time_past(&chain.blocks);
+    if !mempool.add_transaction(tx.clone(), &chain.utxos, height, mtp) {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    state.p2p.broadcast_transaction(&tx);
+
+    Ok(Json(NotarizeResponse {
+        txid: hex::encode(tx.txid()),
+        tx,
+    }))
+}
+
+/// Inclusion proof (header + merkle path) for a confirmed tx
+async fn proof(
+    State(state): State<AppState>,
+    Path(txid): Path<String>,
+) -> Result<Json<InclusionProof>, StatusCode> {
+    let txid = hex::decode(&txid).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let chain = state.chain.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    inclusion_proof(&chain.blocks, &txid)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[derive(Deserialize)]
+struct VerifyProofRequest {
+    /// SHA-256 of the document, hex
+    document_hash: String,
+    proof: InclusionProof,
+}
+
+#[derive(Serialize)]
+struct VerifyProofResponse {
+    /// Header, PoW, document hash and merkle path check out
+    valid: bool,
+    /// The proof's block is on this node's best chain
+    in_best_chain: bool,
+    confirmations: u64,
+}
+
+/// Check an inclusion proof (e.g. from `/proof/:txid`) for a
+/// document hash, and whether its block is on our best chain
+///
+/// `InclusionProof::verify` alone needs no node; this route
+/// adds the best-chain lookup a third party would otherwise
+/// do against a header source they trust.
+async fn verify_proof(
+    State(state): State<AppState>,
+    Json(req): Json<VerifyProofRequest>,
+) -> Result<Json<VerifyProofResponse>, StatusCode> {
+    let document_hash = hex::decode(&req.document_hash)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let valid = req.proof.verify(&document_hash);
+
+    let chain = state.chain.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let height = req.proof.header.height;
+    let in_best_chain = valid
+        && chain
+            .blocks
+            .get(height as usize)
+            .is_some_and(|b| b.hash == req.proof.block_hash);
+
+    let confirmations = if in_best_chain {
+        chain.height() - height
+    } else {
+        0
+    };
+
+    Ok(Json(VerifyProofResponse {
+        valid,
+        in_best_chain,
+        confirmations,
+    }))
+}
@@ -1,26 +1,57 @@
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use crate::core::chain::Blockchain;
-use crate::node::mempool::Mempool;
-use crate::wallet::Wallet;
-use crate::core::validation::validate_transaction;
+use crate::core::chain::{Blockchain, median_time_past};
+use crate::node::mempool::{Mempool, mempool_file};
+use crate::node::p2p::P2PNetwork;
+use crate::wallet::{Wallet, PartialTransaction, find_htlc_preimage, DEFAULT_FEE_PER_BYTE};
+use crate::node::fee_estimator::DEFAULT_CONFIRM_TARGET;
+use crate::core::transaction::Transaction;
 
 const COINBASE_MATURITY: u64 = 100;
 
+/// Time peers get to confirm a pushed transaction
+const PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub fn handle_command(
     args: Vec<String>,
     wallet: &mut Wallet,
     chain: Arc<Mutex<Blockchain>>,
     mempool: Arc<Mutex<Mempool>>,
+    p2p: &P2PNetwork,
 ) {
     if args.len() < 3 {
         println!("Usage:");
         println!("  wallet balance");
-        println!("  wallet send <to_pubkey_hash_hex> <amount>");
+        println!("  wallet address");
+        println!("  wallet send <to_pubkey_hash_hex> <amount> [lock_time]");
+        println!("  wallet bumpfee <txid> [fee_per_byte]");
+        println!("  wallet multisig-fund <m> <pubkey_hash_hex,...> <amount>");
+        println!("  wallet multisig-spend <txid:index> <to_pubkey_hash_hex> <amount> <file>");
+        println!("  wallet multisig-sign <file>");
+        println!("  wallet multisig-submit <file>");
+        println!("  wallet htlc-create <payment_hash_hex> <claim_pubkey_hash_hex> <timeout_height> <amount>");
+        println!("  wallet htlc-claim <txid:index> <preimage_hex>");
+        println!("  wallet htlc-refund <txid:index>");
+        println!("  wallet htlc-preimage <txid:index>");
         return;
     }
 
+    // Fee rate from recent confirmations, if known
+    let estimated_rate = mempool
+        .lock()
+        .unwrap()
+        .fee_estimator()
+        .estimate(DEFAULT_CONFIRM_TARGET)
+        .unwrap_or(DEFAULT_FEE_PER_BYTE);
+    wallet.set_fee_rate(estimated_rate);
+
     match args[2].as_str() {
+        "address" => {
+            let my_hash = wallet.address().expect("wallet locked");
+            println!("👛 {}", hex::encode(my_hash));
+        }
+
         "balance" => {
             let chain_guard = chain.lock().unwrap();
             let my_hash = wallet.address().expect("wallet locked");
@@ -37,9 +68,7 @@ pub fn handle_command(
 
                 total += u.value;
 
-                if !u.is_coinbase {
-                    spendable += u.value;
-                } else if current_height >= u.height + COINBASE_MATURITY {
+                if !u.is_coinbase || current_height >= u.height + COINBASE_MATURITY {
                     spendable += u.value;
                 } else {
                     locked += u.value;
@@ -52,22 +81,33 @@ pub fn handle_command(
         }
 
         "send" => {
-            if args.len() != 5 {
-                println!("Usage: wallet send <to_pubkey_hash_hex> <amount>");
+            if args.len() != 5 && args.len() != 6 {
+                println!("Usage: wallet send <to_pubkey_hash_hex> <amount> [lock_time]");
                 return;
             }
 
             let to = hex::decode(&args[3]).expect("invalid pubkey hash");
             let amount: u64 = args[4].parse().expect("invalid amount");
 
+            // lock_time < 500_000_000 is a height, otherwise a unix time
+            let lock_time: u64 = match args.get(5) {
+                Some(v) => v.parse().expect("invalid lock_time"),
+                None => 0,
+            };
+
+            let mempool_guard = mempool.lock().unwrap();
             let chain_guard = chain.lock().unwrap();
             let current_height = chain_guard.height();
 
+            // Unconfirmed change may be spent (chained payments, CPFP)
+            let view = mempool_guard.utxo_view(&chain_guard.utxos, current_height);
+
             let tx = match wallet.create_transaction(
-                &chain_guard.utxos,
+                &view,
                 to,
                 amount,
                 current_height,
+                lock_time,
             ) {
                 Ok(t) => t,
                 Err(e) => {
@@ -76,23 +116,289 @@ pub fn handle_command(
                 }
             };
 
-            if !validate_transaction(&tx, &chain_guard.utxos, current_height) {
-                println!("❌ Transaction failed consensus validation");
+            drop(chain_guard);
+            drop(mempool_guard);
+            submit(tx, &chain, &mempool, p2p);
+        }
+
+        "bumpfee" => {
+            if args.len() != 4 && args.len() != 5 {
+                println!("Usage: wallet bumpfee <txid> [fee_per_byte]");
                 return;
             }
 
+            let txid = hex::decode(&args[3]).expect("invalid txid");
+            let fee_per_byte: u64 = match args.get(4) {
+                Some(v) => v.parse().expect("invalid fee rate"),
+                None => estimated_rate,
+            };
+
+            let mempool_guard = mempool.lock().unwrap();
+            let chain_guard = chain.lock().unwrap();
+
+            let original = match mempool_guard.get(&txid) {
+                Some(e) => e.tx.clone(),
+                None => {
+                    println!("❌ Transaction not in mempool");
+                    return;
+                }
+            };
+
+            let prevouts = mempool_guard.input_view(&original, &chain_guard.utxos, chain_guard.height());
+
+            let tx = match wallet.bump_fee(&original, &prevouts, fee_per_byte) {
+                Ok(t) => t,
+                Err(e) => {
+                    println!("❌ Wallet error: {}", e);
+                    return;
+                }
+            };
+
             drop(chain_guard);
+            drop(mempool_guard);
+            println!("⛽ Replacement: {}", hex::encode(tx.txid()));
+            submit(tx, &chain, &mempool, p2p);
+        }
+
+        "multisig-fund" => {
+            if args.len() != 6 {
+                println!("Usage: wallet multisig-fund <m> <pubkey_hash_hex,...> <amount>");
+                return;
+            }
+
+            let required: u32 = args[3].parse().expect("invalid m");
+            let pubkey_hashes: Vec<Vec<u8>> = args[4]
+                .split(',')
+                .map(|h| hex::decode(h).expect("invalid pubkey hash"))
+                .collect();
+            let amount: u64 = args[5].parse().expect("invalid amount");
 
-            let mut mempool_guard = mempool.lock().unwrap();
             let chain_guard = chain.lock().unwrap();
+            let current_height = chain_guard.height();
 
-            if mempool_guard.add_transaction(tx, &chain_guard.utxos, current_height) {
-                println!("✅ Transaction added to mempool");
+            let tx = match wallet.create_multisig_funding(
+                &chain_guard.utxos,
+                required,
+                pubkey_hashes,
+                amount,
+                current_height,
+            ) {
+                Ok(t) => t,
+                Err(e) => {
+                    println!("❌ Wallet error: {}", e);
+                    return;
+                }
+            };
+
+            drop(chain_guard);
+            println!("🔐 Multisig output: {}:0", hex::encode(tx.txid()));
+            submit(tx, &chain, &mempool, p2p);
+        }
+
+        "multisig-spend" => {
+            if args.len() != 7 {
+                println!("Usage: wallet multisig-spend <txid:index> <to_pubkey_hash_hex> <amount> <file>");
+                return;
+            }
+
+            let to = hex::decode(&args[4]).expect("invalid pubkey hash");
+            let amount: u64 = args[5].parse().expect("invalid amount");
+
+            let chain_guard = chain.lock().unwrap();
+
+            let partial = match wallet.create_multisig_spend(
+                &chain_guard.utxos,
+                &args[3],
+                to,
+                amount,
+                chain_guard.height(),
+            ) {
+                Ok(p) => p,
+                Err(e) => {
+                    println!("❌ Wallet error: {}", e);
+                    return;
+                }
+            };
+
+            match partial.save(&args[6]) {
+                Ok(()) => println!("📝 Unsigned spend written to {}", args[6]),
+                Err(e) => println!("❌ {}", e),
+            }
+        }
+
+        "multisig-sign" => {
+            if args.len() != 4 {
+                println!("Usage: wallet multisig-sign <file>");
+                return;
+            }
+
+            let mut partial = match PartialTransaction::load(&args[3]) {
+                Ok(p) => p,
+                Err(e) => {
+                    println!("❌ {}", e);
+                    return;
+                }
+            };
+
+            let added = match wallet.cosign(&mut partial) {
+                Ok(n) => n,
+                Err(e) => {
+                    println!("❌ Wallet error: {}", e);
+                    return;
+                }
+            };
+
+            if let Err(e) = partial.save(&args[3]) {
+                println!("❌ {}", e);
+                return;
+            }
+
+            println!("✍️  Added {} signature(s)", added);
+            for (i, (have, need)) in partial.signature_status().iter().enumerate() {
+                println!("   input {}: {}/{} signatures", i, have, need);
+            }
+        }
+
+        "multisig-submit" => {
+            if args.len() != 4 {
+                println!("Usage: wallet multisig-submit <file>");
+                return;
+            }
+
+            let partial = match PartialTransaction::load(&args[3]) {
+                Ok(p) => p,
+                Err(e) => {
+                    println!("❌ {}", e);
+                    return;
+                }
+            };
+
+            if !partial.is_complete() {
+                println!("❌ Not enough signatures yet");
+                return;
+            }
+
+            submit(partial.tx, &chain, &mempool, p2p);
+        }
+
+        "htlc-create" => {
+            if args.len() != 7 {
+                println!("Usage: wallet htlc-create <payment_hash_hex> <claim_pubkey_hash_hex> <timeout_height> <amount>");
+                return;
+            }
+
+            let payment_hash = hex::decode(&args[3]).expect("invalid payment hash");
+            let claim_pubkey_hash = hex::decode(&args[4]).expect("invalid pubkey hash");
+            let timeout_height: u64 = args[5].parse().expect("invalid timeout height");
+            let amount: u64 = args[6].parse().expect("invalid amount");
+
+            let chain_guard = chain.lock().unwrap();
+            let current_height = chain_guard.height();
+
+            let tx = match wallet.create_htlc(
+                &chain_guard.utxos,
+                payment_hash,
+                claim_pubkey_hash,
+                timeout_height,
+                amount,
+                current_height,
+            ) {
+                Ok(t) => t,
+                Err(e) => {
+                    println!("❌ Wallet error: {}", e);
+                    return;
+                }
+            };
+
+            drop(chain_guard);
+            println!("⏳ HTLC output: {}:0", hex::encode(tx.txid()));
+            submit(tx, &chain, &mempool, p2p);
+        }
+
+        "htlc-claim" | "htlc-refund" => {
+            let claim = args[2] == "htlc-claim";
+            if (claim && args.len() != 5) || (!claim && args.len() != 4) {
+                println!("Usage: wallet htlc-claim <txid:index> <preimage_hex>");
+                println!("       wallet htlc-refund <txid:index>");
+                return;
+            }
+
+            let chain_guard = chain.lock().unwrap();
+            let current_height = chain_guard.height();
+
+            let result = if claim {
+                let preimage = hex::decode(&args[4]).expect("invalid preimage");
+                wallet.claim_htlc(&chain_guard.utxos, &args[3], preimage, current_height)
             } else {
-                println!("❌ Transaction rejected by mempool policy");
+                wallet.refund_htlc(&chain_guard.utxos, &args[3], current_height)
+            };
+
+            let tx = match result {
+                Ok(t) => t,
+                Err(e) => {
+                    println!("❌ Wallet error: {}", e);
+                    return;
+                }
+            };
+
+            drop(chain_guard);
+            submit(tx, &chain, &mempool, p2p);
+        }
+
+        "htlc-preimage" => {
+            if args.len() != 4 {
+                println!("Usage: wallet htlc-preimage <txid:index>");
+                return;
+            }
+
+            let chain_guard = chain.lock().unwrap();
+            match find_htlc_preimage(&chain_guard.blocks, &args[3]) {
+                Some(p) => println!("🔑 Preimage: {}", hex::encode(p)),
+                None => println!("❌ No confirmed claim for {}", args[3]),
             }
         }
 
         _ => println!("Unknown wallet command"),
     }
 }
+
+/// Check a wallet-built transaction against the local
+/// mempool and push it to connected peers
+///
+/// The mempool validates spends against confirmed outputs
+/// and outputs of its own entries. If no peer takes the
+/// transaction, it is saved to the mempool file so the node
+/// picks it up on its next start.
+fn submit(
+    tx: Transaction,
+    chain: &Arc<Mutex<Blockchain>>,
+    mempool: &Arc<Mutex<Mempool>>,
+    p2p: &P2PNetwork,
+) {
+    let accepted = {
+        let mut mempool_guard = mempool.lock().unwrap();
+        let chain_guard = chain.lock().unwrap();
+        let current_height = chain_guard.height();
+
+        let mtp = median_time_past(&chain_guard.blocks);
+        mempool_guard.add_transaction(tx.clone(), &chain_guard.utxos, current_height, mtp)
+    };
+
+    if !accepted {
+        println!("❌ Transaction rejected (invalid spend or mempool policy)");
+        return;
+    }
+
+    println!("✅ Transaction valid: {}", hex::encode(tx.txid()));
+
+    let delivered = p2p.push_transaction(&tx, PUSH_TIMEOUT);
+    if delivered > 0 {
+        println!("📡 Delivered to {} peer(s)", delivered);
+        return;
+    }
+
+    match mempool.lock().unwrap().save(&mempool_file()) {
+        Ok(()) => println!("⚠️  No peer reachable. Saved to the local mempool for the node's next start"),
+        Err(e) => println!("❌ No peer reachable and mempool not saved: {}", e),
+    }
+}
@@ -2,6 +2,7 @@ pub mod config;
 pub mod policy;
 pub mod pow;
 pub mod revelation;
+pub mod notary;
 pub mod reward;
 pub mod wallet;
 pub mod wallet_store;
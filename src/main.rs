@@ -4,14 +4,17 @@ use std::thread::sleep;
 use std::time::{Duration, Instant};
 use std::io::{self, Write};
 use std::env;
-use std::net::SocketAddr;
+use std::net::{SocketAddr, ToSocketAddrs};
 
 use tokio::runtime::Runtime;
 use rpassword::read_password;
 
 // ───────── Imports from the LIB crate ─────────
-use bitcoin_v0_2_revelation::core::chain::Blockchain;
+use bitcoin_v0_2_revelation::core::chain::{Blockchain, median_time_past};
+use bitcoin_v0_2_revelation::consensus::shadow::shadow_validate_v5;
 use bitcoin_v0_2_revelation::node::p2p::P2PNetwork;
+use bitcoin_v0_2_revelation::node::addrman::{AddrMan, peers_file};
+use bitcoin_v0_2_revelation::node::transport::{PeerEvent, Transport};
 use bitcoin_v0_2_revelation::node::transport::tcp::TcpTransport;
 use bitcoin_v0_2_revelation::node::transport::satellite::SatelliteTransport;
 #[cfg(feature = "bluetooth")]
@@ -19,10 +22,13 @@ use bitcoin_v0_2_revelation::node::transport::bluetooth::BluetoothTransport;
 use bitcoin_v0_2_revelation::node::transport::geo::GeoTransport;
 use bitcoin_v0_2_revelation::node::dedup::MessageDeduplicator;
 use bitcoin_v0_2_revelation::interface::{api::start_api, cli};
-use bitcoin_v0_2_revelation::node::mempool::Mempool;
+use bitcoin_v0_2_revelation::node::mempool::{Mempool, mempool_file, run_chain_sync};
+use bitcoin_v0_2_revelation::node::fee_estimator::{FeeEstimator, fee_estimates_file};
+use bitcoin_v0_2_revelation::policy::{MEMPOOL_EXPIRY_SECS, MEMPOOL_SAVE_INTERVAL_SECS};
 use bitcoin_v0_2_revelation::wallet::Wallet;
 use bitcoin_v0_2_revelation::wallet_store::load_wallet_store;
 use bitcoin_v0_2_revelation::config::load_miner_config;
+use bitcoin_v0_2_revelation::crypto::sigcache;
 use bitcoin_v0_2_revelation::node::miner;
 
 enum NodeMode {
@@ -42,11 +48,42 @@ const BOOTSTRAP_SEEDS: &[&str] = &[
     "bitcoin-revelation-node.fly.dev:8333",
 ];
 
+fn connect_seeds(transport: &TcpTransport) {
+    for seed in BOOTSTRAP_SEEDS {
+        // Seeds are host names; use the first address that answers
+        let addrs = match seed.to_socket_addrs() {
+            Ok(a) => a,
+            Err(e) => {
+                println!("⚠️  Seed {} not resolved: {}", seed, e);
+                continue;
+            }
+        };
+
+        for addr in addrs {
+            println!("🌱 Connecting to seed {} ({})", seed, addr);
+            if transport.connect(&addr) {
+                break;
+            }
+        }
+    }
+}
+
 fn main() {
     println!("⛓ Bitcoin v0.4.1 — Revelation Edition (Consensus v5)");
 
+    // Read-only diagnostic: no wallet, no network
+    if env::args().nth(1).as_deref() == Some("shadow-v5") {
+        let mut chain = Blockchain::new();
+        chain.initialize();
+
+        let report = shadow_validate_v5(&chain.blocks);
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        return;
+    }
+
     let wallet_store = load_wallet_store();
     let miner_config = load_miner_config();
+    sigcache::global().lock().unwrap().set_max_entries(miner_config.sigcache_entries);
 
     if wallet_store.get_path(&miner_config.coinbase_wallet).is_none() {
         panic!("Configured wallet '{}' not found", miner_config.coinbase_wallet);
@@ -56,6 +93,7 @@ fn main() {
     let password = prompt_secret("🔑 Enter wallet password: ");
 
     let mut wallet = Wallet::load_or_create(&password);
+    wallet.set_policy(miner_config.policy.clone());
     let miner_pubkey_hash = wallet.address().expect("wallet locked");
 
     println!(
@@ -67,32 +105,142 @@ fn main() {
     let mut local_chain = Blockchain::new();
     local_chain.initialize();
 
+    let mut local_mempool =
+        Mempool::with_fee_estimator(FeeEstimator::load(&fee_estimates_file()));
+    local_mempool.set_policy(miner_config.policy.clone());
+    match local_mempool.load(
+        &mempool_file(),
+        &local_chain.utxos,
+        local_chain.height(),
+        median_time_past(&local_chain.blocks),
+    ) {
+        Ok(n) if n > 0 => println!("📥 Restored {} mempool transaction(s)", n),
+        Ok(_) => {}
+        Err(e) => println!("⚠️  Mempool not restored: {}", e),
+    }
+
+    let chain_events = local_chain.subscribe();
+
     let chain = Arc::new(Mutex::new(local_chain));
-    let mempool = Arc::new(Mutex::new(Mempool::new()));
+    let mempool = Arc::new(Mutex::new(local_mempool));
+
+    // 🔁 Mempool follows connects / disconnects (incl. reorgs)
+    {
+        let chain = Arc::clone(&chain);
+        let mempool = Arc::clone(&mempool);
+        thread::spawn(move || run_chain_sync(chain_events, chain, mempool));
+    }
+
+    let p2p_holder: Arc<Mutex<Option<Arc<P2PNetwork>>>> =
+        Arc::new(Mutex::new(None));
+
+    // TCP is ordered & per-connection: no cross-transport dedup
+    // (handshake messages legitimately repeat across peers)
+    let on_receive_tcp = Arc::new({
+        let p2p_holder = Arc::clone(&p2p_holder);
+
+        move |addr: SocketAddr, data: Vec<u8>| {
+            // Holder unlocked while handling: handlers may
+            // disconnect peers, which re-enters via on_peer_event
+            let p2p = p2p_holder.lock().unwrap().clone();
+            if let Some(p2p) = p2p {
+                p2p.on_receive(addr, data);
+            }
+        }
+    });
+
+    let on_peer_event = Arc::new({
+        let p2p_holder = Arc::clone(&p2p_holder);
+
+        move |event: PeerEvent| {
+            let p2p = p2p_holder.lock().unwrap().clone();
+            if let Some(p2p) = p2p {
+                p2p.on_peer_event(event);
+            }
+        }
+    });
 
     let args: Vec<String> = env::args().collect();
     if args.len() > 1 && args[1] == "wallet" {
-        cli::handle_command(args, &mut wallet, Arc::clone(&chain), Arc::clone(&mempool));
+        // Outbound-only, push-only networking: submissions are
+        // handed to the local node (if running) and the seeds.
+        // No listeners, sync or relay that would clash with a node.
+        let transport = TcpTransport::new("0.0.0.0:0", on_receive_tcp, on_peer_event);
+        let p2p = Arc::new(P2PNetwork::push_only(
+            transport.clone(),
+            Arc::clone(&chain),
+            Arc::clone(&mempool),
+        ));
+        *p2p_holder.lock().unwrap() = Some(Arc::clone(&p2p));
+
+        transport.connect(&SocketAddr::from(([127, 0, 0, 1], miner_config.p2p_port)));
+        connect_seeds(&transport);
+        p2p.wait_for_handshakes(Duration::from_secs(5));
+
+        cli::handle_command(args, &mut wallet, Arc::clone(&chain), Arc::clone(&mempool), &p2p);
         return;
     }
 
-    let api_chain = Arc::clone(&chain);
-    thread::spawn(move || {
-        let rt = Runtime::new().expect("Tokio runtime failed");
-        rt.block_on(start_api(api_chain, 8080));
-    });
+    let wallet = Arc::new(Mutex::new(wallet));
 
-    println!("🌐 Explorer running at http://127.0.0.1:8080");
+    // 💾 Mempool + peer persistence: periodic + on Ctrl-C
+    {
+        let mempool = Arc::clone(&mempool);
+        let p2p_holder = Arc::clone(&p2p_holder);
+        thread::spawn(move || loop {
+            sleep(Duration::from_secs(MEMPOOL_SAVE_INTERVAL_SECS));
+            {
+                let mut m = mempool.lock().unwrap();
+                m.expire(MEMPOOL_EXPIRY_SECS);
+                if let Err(e) = m.save(&mempool_file()) {
+                    println!("⚠️  {}", e);
+                }
+                if let Err(e) = m.fee_estimator().save(&fee_estimates_file()) {
+                    println!("⚠️  {}", e);
+                }
+            }
 
-    let p2p_holder: Arc<Mutex<Option<Arc<P2PNetwork>>>> =
-        Arc::new(Mutex::new(None));
+            let p2p = p2p_holder.lock().unwrap().clone();
+            if let Some(Err(e)) = p2p.map(|p| p.save_addresses()) {
+                println!("⚠️  {}", e);
+            }
+        });
+    }
+
+    {
+        let mempool = Arc::clone(&mempool);
+        let p2p_holder = Arc::clone(&p2p_holder);
+        thread::spawn(move || {
+            let rt = Runtime::new().expect("Tokio runtime failed");
+            if rt.block_on(tokio::signal::ctrl_c()).is_ok() {
+                {
+                    let m = mempool.lock().unwrap();
+                    match m.save(&mempool_file()) {
+                        Ok(()) => println!("💾 Mempool saved"),
+                        Err(e) => println!("⚠️  {}", e),
+                    }
+                    if let Err(e) = m.fee_estimator().save(&fee_estimates_file()) {
+                        println!("⚠️  {}", e);
+                    }
+                }
+
+                let p2p = p2p_holder.lock().unwrap().clone();
+                if let Some(Err(e)) = p2p.map(|p| p.save_addresses()) {
+                    println!("⚠️  {}", e);
+                }
+                std::process::exit(0);
+            }
+        });
+    }
 
     let dedup = Arc::new(Mutex::new(
         MessageDeduplicator::new(Duration::from_secs(60))
     ));
 
+    // Broadcast / store-and-forward media (UDP, satellite, BLE)
+    // can deliver the same message several times
     let on_receive = Arc::new({
-        let p2p_holder = Arc::clone(&p2p_holder);
+        let on_receive_tcp = Arc::clone(&on_receive_tcp);
         let dedup = Arc::clone(&dedup);
 
         move |addr: SocketAddr, data: Vec<u8>| {
@@ -101,25 +249,49 @@ fn main() {
                 d.check_and_insert(&data)
             };
 
-            if !is_new {
-                return;
-            }
-
-            if let Some(p2p) = &*p2p_holder.lock().unwrap() {
-                p2p.on_receive(addr, data);
+            if is_new {
+                (on_receive_tcp)(addr, data);
             }
         }
     });
 
-    let transport = TcpTransport::new("0.0.0.0:0", on_receive.clone());
-
-    let p2p = Arc::new(
-        P2PNetwork::new(transport.clone(), Arc::clone(&chain))
+    let transport = TcpTransport::new(
+        &format!("0.0.0.0:{}", miner_config.p2p_port),
+        on_receive_tcp,
+        on_peer_event,
     );
 
+    let p2p = Arc::new(P2PNetwork::listening(
+        transport.clone(),
+        Arc::clone(&chain),
+        Arc::clone(&mempool),
+        AddrMan::load(&peers_file()),
+        miner_config.p2p_port,
+    ));
+
     *p2p_holder.lock().unwrap() = Some(Arc::clone(&p2p));
 
-    println!("🔗 P2P TCP transport initialized");
+    println!("🔗 P2P TCP transport listening on port {}", miner_config.p2p_port);
+
+    // ⏱ Peer housekeeping (handshake timeouts, stalled downloads)
+    {
+        let p2p = Arc::clone(&p2p);
+        thread::spawn(move || loop {
+            sleep(Duration::from_secs(1));
+            p2p.maintain();
+        });
+    }
+
+    let api_chain = Arc::clone(&chain);
+    let api_mempool = Arc::clone(&mempool);
+    let api_wallet = Arc::clone(&wallet);
+    let api_p2p = Arc::clone(&p2p);
+    thread::spawn(move || {
+        let rt = Runtime::new().expect("Tokio runtime failed");
+        rt.block_on(start_api(api_chain, api_mempool, api_wallet, api_p2p, 8080, 8081));
+    });
+
+    println!("🌐 Explorer running at http://127.0.0.1:8080");
 
     SatelliteTransport::listen_udp("0.0.0.0:9999", on_receive.clone());
     GeoTransport::start("0.0.0.0:9333", on_receive.clone());
@@ -134,12 +306,7 @@ fn main() {
         });
     }
 
-    for seed in BOOTSTRAP_SEEDS {
-        if let Ok(addr) = seed.parse::<SocketAddr>() {
-            println!("🌱 Connecting to seed {}", seed);
-            transport.connect(addr);
-        }
-    }
+    connect_seeds(&transport);
 
     println!("🔄 Requesting sync from peers");
 
@@ -158,7 +325,7 @@ fn main() {
                     last_change = Instant::now();
                 }
 
-                if last_change.elapsed() > Duration::from_secs(3) && height > 0 {
+                if last_change.elapsed() > Duration::from_secs(3) && height > 0 && p2p.is_synced() {
                     println!("✅ Sync complete at height {}", height);
                     mode = NodeMode::Normal;
                 }
@@ -167,7 +334,7 @@ fn main() {
             }
 
             NodeMode::Normal => {
-                let txs = mempool.lock().unwrap().sorted_for_mining();
+                let packages = mempool.lock().unwrap().mining_packages();
 
                 let candidate_block = {
                     let c = chain.lock().unwrap();
@@ -175,9 +342,10 @@ fn main() {
                     miner::mine_block(
                         prev,
                         &c.utxos,
-                        txs,
+                        packages,
                         miner_pubkey_hash.clone(),
                         &c.blocks,
+                        &miner_config.policy,
                     )
                 };
 
@@ -189,11 +357,6 @@ fn main() {
                 if accepted {
                     p2p.broadcast_block(&candidate_block);
 
-                    mempool
-                        .lock()
-                        .unwrap()
-                        .remove_confirmed(&candidate_block.transactions);
-
                     let c = chain.lock().unwrap();
                     let balance: u64 = c.utxos
                         .values()
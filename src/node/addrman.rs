@@ -0,0 +1,496 @@
+// ─────────────────────────────────────────────
+// ADDRESS MANAGER (NETWORK ONLY)
+//
+// Known peer addresses, split into two tables:
+//
+// - new:   heard about via Addr, never connected
+// - tried: we completed a handshake with them
+//
+// Each table is a fixed grid of small buckets. The
+// bucket is chosen by a keyed hash of the address's
+// network group (/16, /32 for IPv6) and, for new
+// entries, the group of the peer that told us. One
+// source, or one network, can therefore only fill a
+// few buckets, and outbound selection draws from all
+// of them: an attacker flooding Addr cannot take over
+// our outbound connections (eclipse attack).
+//
+// The hash key is random per node and persisted.
+// ─────────────────────────────────────────────
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+use rand::rngs::ThreadRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::core::chain::data_dir;
+use crate::crypto::sha256;
+
+const NEW_BUCKET_COUNT: usize = 256;
+const TRIED_BUCKET_COUNT: usize = 64;
+const BUCKET_SIZE: usize = 32;
+
+/// New buckets a single source group can reach
+const NEW_BUCKETS_PER_SOURCE_GROUP: u64 = 16;
+
+/// Tried buckets a single address group can reach
+const TRIED_BUCKETS_PER_GROUP: u64 = 4;
+
+/// Most addresses in one Addr message
+pub const MAX_ADDR_PER_MESSAGE: usize = 1_000;
+
+/// Addresses not seen for this long are not gossiped
+const ADDR_HORIZON_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// Minimum time between connection attempts to one address
+const RETRY_INTERVAL_SECS: i64 = 10 * 60;
+
+/// Failed attempts after which a never-reached address is dropped
+const MAX_FAILURES: u32 = 10;
+
+/// How long an address on another chain stays rejected
+const REJECT_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Most rejected addresses remembered
+const MAX_REJECTED: usize = 1_000;
+
+const ADDRMAN_VERSION: u32 = 1;
+
+pub fn peers_file() -> PathBuf {
+    let mut path = data_dir();
+    path.push("peers.json");
+    path
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddrInfo {
+    pub addr: SocketAddr,
+    /// Peer that told us about it (ourselves for direct connections)
+    pub source: IpAddr,
+    pub last_seen: i64,
+    /// 0 = never
+    pub last_success: i64,
+    pub last_attempt: i64,
+    pub attempts: u32,
+    pub tried: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AddrMan {
+    version: u32,
+    key: [u8; 32],
+    entries: HashMap<SocketAddr, AddrInfo>,
+    /// Peers on another chain, not stored again until the
+    /// given time
+    #[serde(default)]
+    rejected_until: HashMap<SocketAddr, i64>,
+
+    // Derived from `entries`; rebuilt on load
+    #[serde(skip)]
+    new_buckets: Vec<Vec<SocketAddr>>,
+    #[serde(skip)]
+    tried_buckets: Vec<Vec<SocketAddr>>,
+}
+
+impl Default for AddrMan {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Network group: IPv4 /16, IPv6 /32
+fn group(ip: &IpAddr) -> Vec<u8> {
+    match ip {
+        IpAddr::V4(v4) => {
+            let o = v4.octets();
+            vec![4, o[0], o[1]]
+        }
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => group(&IpAddr::V4(v4)),
+            None => {
+                let o = v6.octets();
+                vec![6, o[0], o[1], o[2], o[3]]
+            }
+        },
+    }
+}
+
+/// Addresses worth storing / dialing
+fn is_routable(addr: &SocketAddr) -> bool {
+    addr.port() != 0 && !addr.ip().is_unspecified() && !addr.ip().is_multicast()
+}
+
+impl AddrMan {
+    pub fn new() -> Self {
+        Self {
+            version: ADDRMAN_VERSION,
+            key: rand::thread_rng().gen(),
+            entries: HashMap::new(),
+            rejected_until: HashMap::new(),
+            new_buckets: vec![Vec::new(); NEW_BUCKET_COUNT],
+            tried_buckets: vec![Vec::new(); TRIED_BUCKET_COUNT],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Keyed hash of `parts`, as a number
+    fn hash(&self, parts: &[&[u8]]) -> u64 {
+        let mut data = self.key.to_vec();
+        for p in parts {
+            data.extend_from_slice(&(p.len() as u32).to_le_bytes());
+            data.extend_from_slice(p);
+        }
+        let h = sha256(&data);
+        u64::from_le_bytes(h[..8].try_into().unwrap())
+    }
+
+    fn is_rejected(&self, addr: &SocketAddr, now: i64) -> bool {
+        self.rejected_until.get(addr).is_some_and(|until| now < *until)
+    }
+
+    fn new_bucket(&self, addr: &SocketAddr, source: &IpAddr) -> usize {
+        let addr_group = group(&addr.ip());
+        let source_group = group(source);
+
+        let slot = self.hash(&[&addr_group, &source_group]) % NEW_BUCKETS_PER_SOURCE_GROUP;
+        (self.hash(&[&source_group, &slot.to_le_bytes()]) % NEW_BUCKET_COUNT as u64) as usize
+    }
+
+    fn tried_bucket(&self, addr: &SocketAddr) -> usize {
+        let addr_group = group(&addr.ip());
+
+        let slot = self.hash(&[addr.to_string().as_bytes()]) % TRIED_BUCKETS_PER_GROUP;
+        (self.hash(&[&addr_group, &slot.to_le_bytes()]) % TRIED_BUCKET_COUNT as u64) as usize
+    }
+
+    /// Place an entry in its bucket, evicting the stalest
+    /// occupant if the bucket is full
+    fn place(&mut self, info: AddrInfo) {
+        let addr = info.addr;
+
+        if info.tried {
+            let b = self.tried_bucket(&addr);
+
+            if self.tried_buckets[b].len() >= BUCKET_SIZE {
+                // Oldest success goes back to the new table
+                let oldest = self.tried_buckets[b]
+                    .iter()
+                    .min_by_key(|a| self.entries[*a].last_success)
+                    .copied();
+
+                if let Some(old) = oldest {
+                    self.tried_buckets[b].retain(|a| *a != old);
+                    if let Some(mut demoted) = self.entries.remove(&old) {
+                        demoted.tried = false;
+                        self.place(demoted);
+                    }
+                }
+            }
+
+            self.tried_buckets[b].push(addr);
+        } else {
+            let b = self.new_bucket(&addr, &info.source);
+
+            if self.new_buckets[b].len() >= BUCKET_SIZE {
+                let oldest = self.new_buckets[b]
+                    .iter()
+                    .min_by_key(|a| self.entries[*a].last_seen)
+                    .copied();
+
+                if let Some(old) = oldest {
+                    self.new_buckets[b].retain(|a| *a != old);
+                    self.entries.remove(&old);
+                }
+            }
+
+            self.new_buckets[b].push(addr);
+        }
+
+        self.entries.insert(addr, info);
+    }
+
+    /// Take an entry out of its bucket and the table
+    fn unplace(&mut self, addr: &SocketAddr) -> Option<AddrInfo> {
+        let info = self.entries.remove(addr)?;
+
+        let bucket = if info.tried {
+            let b = self.tried_bucket(addr);
+            &mut self.tried_buckets[b]
+        } else {
+            let b = self.new_bucket(addr, &info.source);
+            &mut self.new_buckets[b]
+        };
+        bucket.retain(|a| a != addr);
+
+        Some(info)
+    }
+
+    /// Learn addresses gossiped by `source`
+    ///
+    /// Returns how many were new to us.
+    pub fn add(&mut self, addrs: &[SocketAddr], source: IpAddr, now: i64) -> usize {
+        let mut added = 0;
+
+        for addr in addrs.iter().take(MAX_ADDR_PER_MESSAGE) {
+            if !is_routable(addr) || self.is_rejected(addr, now) {
+                continue;
+            }
+
+            if let Some(info) = self.entries.get_mut(addr) {
+                info.last_seen = info.last_seen.max(now);
+                continue;
+            }
+
+            self.place(AddrInfo {
+                addr: *addr,
+                source,
+                last_seen: now,
+                last_success: 0,
+                last_attempt: 0,
+                attempts: 0,
+                tried: false,
+            });
+            added += 1;
+        }
+
+        added
+    }
+
+    /// Handshake completed with an outbound peer: move to tried
+    pub fn mark_good(&mut self, addr: SocketAddr, now: i64) {
+        if !is_routable(&addr) || self.is_rejected(&addr, now) {
+            return;
+        }
+
+        let mut info = self.unplace(&addr).unwrap_or(AddrInfo {
+            addr,
+            source: addr.ip(),
+            last_seen: now,
+            last_success: 0,
+            last_attempt: now,
+            attempts: 0,
+            tried: false,
+        });
+
+        info.last_seen = now;
+        info.last_success = now;
+        info.attempts = 0;
+        info.tried = true;
+        self.place(info);
+    }
+
+    /// About to dial `addr`
+    pub fn mark_attempt(&mut self, addr: SocketAddr, now: i64) {
+        let drop = match self.entries.get_mut(&addr) {
+            Some(info) => {
+                info.last_attempt = now;
+                info.attempts += 1;
+                info.last_success == 0 && info.attempts >= MAX_FAILURES
+            }
+            None => false,
+        };
+
+        if drop {
+            self.unplace(&addr);
+        }
+    }
+
+    /// Forget a peer on another chain and ignore it for
+    /// REJECT_TTL_SECS
+    ///
+    /// At most MAX_REJECTED are kept; the one expiring first
+    /// makes room.
+    pub fn reject(&mut self, addr: SocketAddr, now: i64) {
+        self.unplace(&addr);
+
+        self.rejected_until.retain(|_, until| now < *until);
+        if self.rejected_until.len() >= MAX_REJECTED && !self.rejected_until.contains_key(&addr) {
+            let first = self.rejected_until.iter().min_by_key(|(_, until)| **until).map(|(a, _)| *a);
+            if let Some(first) = first {
+                self.rejected_until.remove(&first);
+            }
+        }
+
+        self.rejected_until.insert(addr, now + REJECT_TTL_SECS);
+    }
+
+    /// Pick an address to dial, half the time from each table
+    ///
+    /// A random bucket is chosen first, then an entry in it, so
+    /// a crowded bucket weighs no more than a sparse one.
+    /// Skips `exclude` (already connected) and addresses
+    /// attempted within RETRY_INTERVAL_SECS.
+    pub fn select(&self, exclude: &HashSet<SocketAddr>, now: i64) -> Option<SocketAddr> {
+        let mut rng = rand::thread_rng();
+
+        let usable = |a: &SocketAddr| {
+            !exclude.contains(a) && now - self.entries[a].last_attempt >= RETRY_INTERVAL_SECS
+        };
+
+        let pick = |buckets: &[Vec<SocketAddr>], rng: &mut ThreadRng| -> Option<SocketAddr> {
+            let candidates: Vec<Vec<SocketAddr>> = buckets
+                .iter()
+                .map(|b| b.iter().filter(|a| usable(a)).copied().collect::<Vec<_>>())
+                .filter(|b| !b.is_empty())
+                .collect();
+
+            candidates.choose(rng)?.choose(rng).copied()
+        };
+
+        let (first, second) = if rng.gen_bool(0.5) {
+            (&self.tried_buckets, &self.new_buckets)
+        } else {
+            (&self.new_buckets, &self.tried_buckets)
+        };
+
+        pick(first, &mut rng).or_else(|| pick(second, &mut rng))
+    }
+
+    /// Recently seen addresses for a GetAddr reply
+    pub fn get_addr(&self, now: i64) -> Vec<SocketAddr> {
+        let mut addrs: Vec<SocketAddr> = self
+            .entries
+            .values()
+            .filter(|i| now - i.last_seen <= ADDR_HORIZON_SECS)
+            .map(|i| i.addr)
+            .collect();
+
+        addrs.shuffle(&mut rand::thread_rng());
+        addrs.truncate(MAX_ADDR_PER_MESSAGE);
+        addrs
+    }
+
+    pub fn load(path: &Path) -> Self {
+        let loaded: Option<Self> = fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok());
+
+        let stored = match loaded {
+            Some(s) if s.version == ADDRMAN_VERSION => s,
+            _ => return Self::new(),
+        };
+
+        // Re-bucket (bucket layout may have changed)
+        let mut addrman = Self {
+            key: stored.key,
+            rejected_until: stored.rejected_until,
+            ..Self::new()
+        };
+        for info in stored.entries.into_values() {
+            addrman.place(info);
+        }
+        addrman
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), &'static str> {
+        let data = serde_json::to_string(self).map_err(|_| "peer database encode failed")?;
+
+        // Write-then-rename so a crash never leaves a torn file
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data).map_err(|_| "peer database write failed")?;
+        fs::rename(&tmp, path).map_err(|_| "peer database write failed")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(a: u8, b: u8, c: u8, port: u16) -> SocketAddr {
+        SocketAddr::from(([a, b, c, 1], port))
+    }
+
+    #[test]
+    fn rejection_expires() {
+        let mut addrman = AddrMan::new();
+        let peer = addr(10, 1, 1, 8333);
+        let source = addr(10, 2, 2, 8333).ip();
+
+        addrman.reject(peer, 0);
+        assert_eq!(addrman.add(&[peer], source, 1), 0);
+        assert_eq!(addrman.add(&[peer], source, REJECT_TTL_SECS), 1);
+    }
+
+    #[test]
+    fn rejected_set_is_capped() {
+        let mut addrman = AddrMan::new();
+
+        for i in 0..MAX_REJECTED + 10 {
+            addrman.reject(addr(10, (i / 256) as u8, i as u8, 8333), i as i64);
+        }
+
+        assert_eq!(addrman.rejected_until.len(), MAX_REJECTED);
+        assert!(addrman.is_rejected(&addr(10, 3, 241, 8333), MAX_REJECTED as i64));
+        assert!(!addrman.is_rejected(&addr(10, 0, 0, 8333), MAX_REJECTED as i64));
+    }
+
+    #[test]
+    fn select_skips_excluded_and_recent_attempts() {
+        let mut addrman = AddrMan::new();
+        let (a, b) = (addr(10, 1, 1, 8333), addr(20, 1, 1, 8333));
+        addrman.add(&[a, b], addr(30, 1, 1, 8333).ip(), 0);
+
+        let now = 1_700_000_000;
+        let exclude: HashSet<SocketAddr> = [a].into_iter().collect();
+        assert_eq!(addrman.select(&exclude, now), Some(b));
+
+        addrman.mark_attempt(b, now);
+        assert_eq!(addrman.select(&exclude, now + 1), None);
+        assert_eq!(addrman.select(&exclude, now + RETRY_INTERVAL_SECS), Some(b));
+    }
+
+    #[test]
+    fn select_weighs_buckets_not_entries() {
+        let mut addrman = AddrMan::new();
+
+        // One source filling one bucket with a single group
+        let flood_source = addr(66, 6, 6, 8333).ip();
+        let flood: Vec<SocketAddr> = (0..BUCKET_SIZE as u8).map(|i| addr(66, 6, i, 8333)).collect();
+        addrman.add(&flood, flood_source, 0);
+        let flooded = addrman.new_bucket(&flood[0], &flood_source);
+
+        // One honest address in another bucket
+        let (honest, source) = (1..=255u8)
+            .map(|i| (addr(i, 1, 1, 8333), addr(i, 2, 2, 8333).ip()))
+            .find(|(a, s)| addrman.new_bucket(a, s) != flooded)
+            .unwrap();
+        addrman.add(&[honest], source, 0);
+
+        let picks = (0..400)
+            .filter(|_| addrman.select(&HashSet::new(), RETRY_INTERVAL_SECS) == Some(honest))
+            .count();
+
+        // Half of the picks by bucket; 1 in 33 by entry
+        assert!((120..280).contains(&picks), "{} of 400", picks);
+    }
+
+    #[test]
+    fn reload_keeps_entries_and_rejections() {
+        let mut addrman = AddrMan::new();
+        let (kept, rejected) = (addr(10, 1, 1, 8333), addr(20, 1, 1, 8333));
+        addrman.add(&[kept], addr(30, 1, 1, 8333).ip(), 0);
+        addrman.reject(rejected, 0);
+
+        let path = std::env::temp_dir().join(format!("addrman-test-{}.json", std::process::id()));
+        addrman.save(&path).unwrap();
+        let mut loaded = AddrMan::load(&path);
+        fs::remove_file(&path).ok();
+
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded.add(&[rejected], kept.ip(), 1), 0);
+    }
+}
@@ -0,0 +1,350 @@
+// ─────────────────────────────────────────────
+// FEE ESTIMATION (POLICY ONLY)
+//
+// Tracks how many blocks mempool transactions in
+// each fee-rate bucket took to confirm, and answers
+// "fee rate to confirm within N blocks".
+//
+// Estimates never affect validation.
+// ─────────────────────────────────────────────
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Serialize, Deserialize};
+
+use crate::core::block::Block;
+use crate::core::chain::data_dir;
+
+/// Longest confirmation target tracked (blocks)
+pub const MAX_CONFIRM_TARGET: usize = 48;
+
+/// Target used when a caller does not choose one
+pub const DEFAULT_CONFIRM_TARGET: usize = 6;
+
+/// Highest bucket lower bound (fee per byte)
+const MAX_BUCKET_FEE: f64 = 10_000.0;
+const BUCKET_SPACING: f64 = 1.5;
+
+/// Per-block decay so old blocks count less
+const DECAY: f64 = 0.998;
+
+/// Fraction of a bucket's transactions that must confirm in time
+const SUCCESS_THRESHOLD: f64 = 0.85;
+
+/// Decayed samples needed before a bucket (or pooled range) counts
+const MIN_SAMPLES: f64 = 4.0;
+
+const FEE_STATS_VERSION: u32 = 1;
+
+pub fn fee_estimates_file() -> PathBuf {
+    let mut path = data_dir();
+    path.push("fee_estimates.json");
+    path
+}
+
+/// Lower bound (fee per byte) of every bucket, ascending
+fn bucket_bounds() -> Vec<f64> {
+    let mut bounds = vec![1.0];
+    while *bounds.last().unwrap() * BUCKET_SPACING <= MAX_BUCKET_FEE {
+        bounds.push(bounds.last().unwrap() * BUCKET_SPACING);
+    }
+    bounds
+}
+
+/// A mempool transaction awaiting confirmation
+struct Tracked {
+    bucket: usize,
+    entry_height: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FeeEstimator {
+    version: u32,
+    bounds: Vec<f64>,
+    /// confirmed[b][n]: bucket b txs confirmed within n+1 blocks
+    confirmed: Vec<Vec<f64>>,
+    /// Every confirmed tx per bucket
+    total: Vec<f64>,
+    best_height: u64,
+
+    /// Not persisted: only the confirmed / total statistics
+    /// above are saved. Entries are tracked again as the
+    /// saved mempool is reloaded on startup.
+    #[serde(skip)]
+    tracked: HashMap<Vec<u8>, Tracked>,
+}
+
+impl Default for FeeEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FeeEstimator {
+    pub fn new() -> Self {
+        let bounds = bucket_bounds();
+        let buckets = bounds.len();
+
+        Self {
+            version: FEE_STATS_VERSION,
+            bounds,
+            confirmed: vec![vec![0.0; MAX_CONFIRM_TARGET]; buckets],
+            total: vec![0.0; buckets],
+            best_height: 0,
+            tracked: HashMap::new(),
+        }
+    }
+
+    fn bucket_for(&self, fee_per_byte: f64) -> usize {
+        self.bounds
+            .iter()
+            .rposition(|b| fee_per_byte >= *b)
+            .unwrap_or(0)
+    }
+
+    /// A transaction entered the mempool while `height` was next
+    pub fn track(&mut self, txid: &[u8], fee: i64, size: usize, height: u64) {
+        if size == 0 || self.tracked.contains_key(txid) {
+            return;
+        }
+
+        let bucket = self.bucket_for(fee as f64 / size as f64);
+        self.tracked.insert(txid.to_vec(), Tracked { bucket, entry_height: height });
+    }
+
+    /// A transaction left the mempool without confirming
+    pub fn forget(&mut self, txid: &[u8]) {
+        self.tracked.remove(txid);
+    }
+
+    /// Record confirmations from a newly connected block
+    pub fn process_block(&mut self, block: &Block) {
+        let height = block.header.height;
+
+        // Reorgs re-connect old heights; only new tips count
+        if height <= self.best_height {
+            for tx in &block.transactions {
+                self.tracked.remove(&tx.txid());
+            }
+            return;
+        }
+        self.best_height = height;
+
+        for row in &mut self.confirmed {
+            row.iter_mut().for_each(|c| *c *= DECAY);
+        }
+        self.total.iter_mut().for_each(|t| *t *= DECAY);
+
+        for tx in &block.transactions {
+            let t = match self.tracked.remove(&tx.txid()) {
+                Some(t) => t,
+                None => continue,
+            };
+
+            // Entered while `entry_height` was next: that block is 1
+            let blocks = (height + 1).saturating_sub(t.entry_height).max(1) as usize;
+
+            self.total[t.bucket] += 1.0;
+            for n in (blocks - 1)..MAX_CONFIRM_TARGET {
+                self.confirmed[t.bucket][n] += 1.0;
+            }
+        }
+    }
+
+    /// Fee per byte likely to confirm within `target` blocks
+    ///
+    /// Walks buckets from the highest fee rate down, pooling
+    /// sparse buckets, and returns the lowest rate whose pooled
+    /// success rate stays above SUCCESS_THRESHOLD. Transactions
+    /// still waiting longer than `target` count as failures.
+    pub fn estimate(&self, target: usize) -> Option<u64> {
+        if target == 0 || target > MAX_CONFIRM_TARGET {
+            return None;
+        }
+
+        let mut waiting = vec![0.0; self.bounds.len()];
+        for t in self.tracked.values() {
+            if self.best_height + 1 >= t.entry_height + target as u64 {
+                waiting[t.bucket] += 1.0;
+            }
+        }
+
+        let mut best = None;
+        let mut pooled_ok = 0.0;
+        let mut pooled_all = 0.0;
+
+        for b in (0..self.bounds.len()).rev() {
+            pooled_ok += self.confirmed[b][target - 1];
+            pooled_all += self.total[b] + waiting[b];
+
+            if pooled_all < MIN_SAMPLES {
+                continue;
+            }
+
+            if pooled_ok / pooled_all < SUCCESS_THRESHOLD {
+                break;
+            }
+
+            best = Some(self.bounds[b].ceil() as u64);
+            pooled_ok = 0.0;
+            pooled_all = 0.0;
+        }
+
+        best
+    }
+
+    /// Load saved statistics, or start empty
+    pub fn load(path: &Path) -> Self {
+        let loaded: Option<Self> = fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok());
+
+        match loaded {
+            Some(s) if s.version == FEE_STATS_VERSION && s.bounds == bucket_bounds() => s,
+            _ => Self::new(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), &'static str> {
+        let data = serde_json::to_string(self).map_err(|_| "fee stats encode failed")?;
+
+        // Write-then-rename so a crash never leaves a torn file
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data).map_err(|_| "fee stats write failed")?;
+        fs::rename(&tmp, path).map_err(|_| "fee stats write failed")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::block::BlockHeader;
+    use crate::core::transaction::{Transaction, TxOutput};
+    use crate::consensus::params::MAX_TARGET;
+
+    const SIZE: usize = 200;
+
+    /// Distinct stand-in transactions (`tag` keeps groups apart)
+    fn txs(tag: u8, count: u64) -> Vec<Transaction> {
+        (0..count)
+            .map(|i| Transaction::new(Vec::new(), vec![TxOutput::pay_to_pubkey_hash(i, vec![tag; 32])]))
+            .collect()
+    }
+
+    fn block(height: u64, transactions: Vec<Transaction>) -> Block {
+        Block {
+            header: BlockHeader {
+                version: 0,
+                height,
+                timestamp: 0,
+                prev_hash: Vec::new(),
+                nonce: 0,
+                target: MAX_TARGET,
+                merkle_root: Vec::new(),
+            },
+            transactions,
+            hash: Vec::new(),
+        }
+    }
+
+    fn track_all(est: &mut FeeEstimator, txs: &[Transaction], fee_per_byte: i64, height: u64) {
+        for tx in txs {
+            est.track(&tx.txid(), fee_per_byte * SIZE as i64, SIZE, height);
+        }
+    }
+
+    /// Rate `estimate` reports for a bucket holding `fee_per_byte`
+    fn bucket_rate(est: &FeeEstimator, fee_per_byte: f64) -> u64 {
+        est.bounds[est.bucket_for(fee_per_byte)].ceil() as u64
+    }
+
+    /// 10 txs at 20/byte confirming next block, 10 at 5/byte
+    /// confirming after 3 blocks
+    fn two_tier() -> FeeEstimator {
+        let mut est = FeeEstimator::new();
+        let (fast, slow) = (txs(1, 10), txs(2, 10));
+
+        track_all(&mut est, &fast, 20, 100);
+        track_all(&mut est, &slow, 5, 100);
+
+        est.process_block(&block(100, fast));
+        est.process_block(&block(101, Vec::new()));
+        est.process_block(&block(102, slow));
+        est
+    }
+
+    #[test]
+    fn not_enough_data() {
+        let mut est = FeeEstimator::new();
+        assert_eq!(est.estimate(1), None);
+
+        let few = txs(1, 3);
+        track_all(&mut est, &few, 20, 100);
+        est.process_block(&block(100, few));
+        assert_eq!(est.estimate(1), None);
+
+        assert_eq!(est.estimate(0), None);
+        assert_eq!(est.estimate(MAX_CONFIRM_TARGET + 1), None);
+    }
+
+    #[test]
+    fn bucket_threshold_depends_on_target() {
+        let est = two_tier();
+
+        assert_eq!(est.estimate(1), Some(bucket_rate(&est, 20.0)));
+        assert_eq!(est.estimate(2), Some(bucket_rate(&est, 20.0)));
+        assert_eq!(est.estimate(3), Some(bucket_rate(&est, 5.0)));
+        assert_eq!(est.estimate(MAX_CONFIRM_TARGET), Some(bucket_rate(&est, 5.0)));
+    }
+
+    #[test]
+    fn unconfirmed_transactions_count_as_failures() {
+        let mut est = two_tier();
+
+        // 5/byte stops confirming within 3 blocks
+        let stuck = txs(3, 10);
+        track_all(&mut est, &stuck, 5, 103);
+        for h in 103..106 {
+            est.process_block(&block(h, Vec::new()));
+        }
+
+        assert_eq!(est.estimate(3), Some(bucket_rate(&est, 20.0)));
+    }
+
+    #[test]
+    fn old_statistics_decay() {
+        let mut est = two_tier();
+
+        // 20 × 0.998^900 ≈ 3.3 samples left, even pooled:
+        // below MIN_SAMPLES
+        for h in 103..1003 {
+            est.process_block(&block(h, Vec::new()));
+        }
+
+        assert_eq!(est.estimate(1), None);
+        assert_eq!(est.estimate(3), None);
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let mut est = two_tier();
+        let pending = txs(3, 1);
+        track_all(&mut est, &pending, 20, 103);
+
+        let path = std::env::temp_dir().join(format!("fee-estimates-test-{}.json", std::process::id()));
+        est.save(&path).unwrap();
+        let loaded = FeeEstimator::load(&path);
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(loaded.best_height, 102);
+        assert_eq!(loaded.estimate(1), est.estimate(1));
+        assert_eq!(loaded.estimate(3), est.estimate(3));
+
+        // Tracked entries are not persisted
+        assert!(loaded.tracked.is_empty());
+    }
+}
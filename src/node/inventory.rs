@@ -0,0 +1,38 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::node::message::InvItem;
+
+/// Items remembered per peer before the oldest are forgotten
+pub const MAX_KNOWN_INVENTORY: usize = 5_000;
+
+/// Inventory a peer is known to have (sent to or received from it)
+///
+/// Used to skip announcing objects back to peers that already
+/// have them. Bounded; oldest entries are forgotten first, which
+/// at worst causes one redundant `Inv`.
+#[derive(Default)]
+pub struct KnownInventory {
+    items: HashSet<InvItem>,
+    order: VecDeque<InvItem>,
+}
+
+impl KnownInventory {
+    pub fn contains(&self, item: &InvItem) -> bool {
+        self.items.contains(item)
+    }
+
+    /// Returns false if the item was already known
+    pub fn insert(&mut self, item: InvItem) -> bool {
+        if !self.items.insert(item.clone()) {
+            return false;
+        }
+
+        self.order.push_back(item);
+        while self.order.len() > MAX_KNOWN_INVENTORY {
+            if let Some(old) = self.order.pop_front() {
+                self.items.remove(&old);
+            }
+        }
+        true
+    }
+}
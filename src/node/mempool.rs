@@ -1,122 +1,619 @@
 use crate::transaction::Transaction;
-use crate::utxo::UTXOSet;
-use crate::policy::MAX_TX_SIZE;
-use crate::validation::validate_transaction;
+use crate::utxo::{UTXOSet, UTXO, outpoint_key, apply_transaction};
+use crate::policy::{
+    MAX_TX_SIZE, PolicyConfig, is_standard,
+    MAX_ANCESTORS, MAX_DESCENDANTS, MAX_ANCESTOR_SIZE, MAX_DESCENDANT_SIZE,
+    INCREMENTAL_RELAY_FEE_PER_BYTE, MAX_REPLACEMENT_EVICTIONS, MEMPOOL_EXPIRY_SECS,
+};
+use crate::core::chain::{data_dir, median_time_past, Blockchain, ChainEvent};
+use crate::validation::{validate_transaction, is_final_transaction};
 use crate::block::Block;
+use crate::node::fee_estimator::FeeEstimator;
+use crate::node::orphans::OrphanPool;
 
-use std::collections::HashSet;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::Receiver;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use serde::{Serialize, Deserialize};
+
 const MAX_MEMPOOL_TXS: usize = 50_000;
 
+/* ───────── Persistence ───────── */
+
+const MEMPOOL_FILE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct MempoolFile {
+    version: u32,
+    entries: Vec<PersistedEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedEntry {
+    tx: Transaction,
+    timestamp: i64,
+}
+
+pub fn mempool_file() -> PathBuf {
+    let mut path = data_dir();
+    path.push("mempool.dat");
+    path
+}
+
 #[derive(Clone)]
 pub struct MempoolEntry {
+    pub txid: Vec<u8>,
     pub tx: Transaction,
     pub fee: i64,
     pub size: usize,
     pub timestamp: i64,
+
+    /// In-mempool transactions this entry spends from
+    pub parents: HashSet<Vec<u8>>,
+    /// In-mempool transactions spending this entry
+    pub children: HashSet<Vec<u8>>,
+
+    /// Totals over this entry and all in-mempool ancestors
+    pub ancestor_count: usize,
+    pub ancestor_size: usize,
+    pub ancestor_fee: i64,
+
+    /// Totals over this entry and all in-mempool descendants
+    pub descendant_count: usize,
+    pub descendant_size: usize,
+    pub descendant_fee: i64,
 }
 
+/// Fee-rate index key (lowest fee-rate first, ties by txid)
+#[derive(Clone, PartialEq, Eq)]
+struct FeeRateKey {
+    fee: i64,
+    size: usize,
+    txid: Vec<u8>,
+}
+
+impl FeeRateKey {
+    /// Eviction score: the entry together with its descendants
+    fn of(entry: &MempoolEntry) -> Self {
+        Self {
+            fee: entry.descendant_fee,
+            size: entry.descendant_size,
+            txid: entry.txid.clone(),
+        }
+    }
+}
+
+impl Ord for FeeRateKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // fee / size compared without division
+        let lhs = self.fee as i128 * other.size as i128;
+        let rhs = other.fee as i128 * self.size as i128;
+        lhs.cmp(&rhs).then_with(|| self.txid.cmp(&other.txid))
+    }
+}
+
+impl PartialOrd for FeeRateKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Transaction pool (POLICY ONLY)
+///
+/// - entries:          txid → entry (with parent/child links)
+/// - by_fee_rate:      descendant fee-rate index for eviction
+/// - spent_outpoints:  outpoint → spending txid
+///
+/// Entries may spend outputs of other entries (unconfirmed
+/// chains), bounded by the ancestor/descendant limits in
+/// `policy`. Mining uses ancestor fee-rate packages, so a
+/// high-fee child pulls its low-fee parent into a block.
+///
+/// A transaction conflicting with existing spends replaces
+/// them (and their descendants) if it pays more; see
+/// `check_replacement`.
 pub struct Mempool {
-    entries: Vec<MempoolEntry>,
-    spent_outpoints: HashSet<(Vec<u8>, u32)>,
+    entries: HashMap<Vec<u8>, MempoolEntry>,
+    by_fee_rate: BTreeSet<FeeRateKey>,
+    spent_outpoints: HashMap<(Vec<u8>, u32), Vec<u8>>,
+    fee_estimator: FeeEstimator,
+    orphans: OrphanPool,
+    policy: PolicyConfig,
+}
+
+/// Result of offering a relayed transaction
+pub enum TxAcceptance {
+    /// Added; `resolved` are orphans it unblocked, also added
+    Accepted { resolved: Vec<Transaction> },
+    /// Parents unknown; held in the orphan pool
+    Orphaned,
+    Rejected,
+}
+
+impl Default for Mempool {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Mempool {
     pub fn new() -> Self {
         Self {
-            entries: Vec::new(),
-            spent_outpoints: HashSet::new(),
+            entries: HashMap::new(),
+            by_fee_rate: BTreeSet::new(),
+            spent_outpoints: HashMap::new(),
+            fee_estimator: FeeEstimator::new(),
+            orphans: OrphanPool::new(),
+            policy: PolicyConfig::default(),
+        }
+    }
+
+    /// Standardness rules for new entries (per node)
+    pub fn set_policy(&mut self, policy: PolicyConfig) {
+        self.policy = policy;
+    }
+
+    /// Use previously saved fee statistics
+    pub fn with_fee_estimator(fee_estimator: FeeEstimator) -> Self {
+        Self {
+            fee_estimator,
+            ..Self::new()
         }
     }
 
+    pub fn fee_estimator(&self) -> &FeeEstimator {
+        &self.fee_estimator
+    }
+
     pub fn size(&self) -> usize {
         self.entries.len()
     }
 
+    pub fn contains(&self, txid: &[u8]) -> bool {
+        self.entries.contains_key(txid)
+    }
+
+    pub fn get(&self, txid: &[u8]) -> Option<&MempoolEntry> {
+        self.entries.get(txid)
+    }
+
+    /// In the pool or waiting as an orphan
+    pub fn knows(&self, txid: &[u8]) -> bool {
+        self.entries.contains_key(txid) || self.orphans.contains(txid)
+    }
+
+    pub fn orphan_count(&self) -> usize {
+        self.orphans.len()
+    }
+
+    /// Add a transaction, then retry orphans waiting on it
     pub fn add_transaction(
         &mut self,
         tx: Transaction,
         utxos: &UTXOSet,
         chain_height: u64,
+        median_time_past: i64,
+    ) -> bool {
+        let txid = tx.txid();
+
+        if !self.try_add(tx, utxos, chain_height, median_time_past) {
+            return false;
+        }
+
+        self.process_orphans(vec![txid], utxos, chain_height, median_time_past);
+        true
+    }
+
+    /// Add a relayed transaction, holding it as an orphan
+    /// if any parent is unknown
+    pub fn accept_from_peer(
+        &mut self,
+        tx: Transaction,
+        peer: Option<SocketAddr>,
+        utxos: &UTXOSet,
+        chain_height: u64,
+        median_time_past: i64,
+    ) -> TxAcceptance {
+        let txid = tx.txid();
+        if self.entries.contains_key(&txid) || self.orphans.contains(&txid) {
+            return TxAcceptance::Rejected;
+        }
+
+        let missing = self.missing_parents(&tx, utxos);
+        if !missing.is_empty() {
+            if tx.serialized_size() <= MAX_TX_SIZE
+                && self.orphans.add(tx, peer, missing, now())
+            {
+                return TxAcceptance::Orphaned;
+            }
+            return TxAcceptance::Rejected;
+        }
+
+        if !self.try_add(tx, utxos, chain_height, median_time_past) {
+            return TxAcceptance::Rejected;
+        }
+
+        let resolved = self.process_orphans(vec![txid], utxos, chain_height, median_time_past);
+        TxAcceptance::Accepted { resolved }
+    }
+
+    /// Retry orphans spending a connected block's outputs
+    pub fn retry_orphans_for_block(
+        &mut self,
+        block: &Block,
+        utxos: &UTXOSet,
+        chain_height: u64,
+        median_time_past: i64,
+    ) -> Vec<Transaction> {
+        self.orphans.expire(now());
+
+        let parents = block.transactions.iter().map(|tx| tx.txid()).collect();
+        self.process_orphans(parents, utxos, chain_height, median_time_past)
+    }
+
+    /// Drop orphans relayed by a peer that went away
+    pub fn remove_orphans_for_peer(&mut self, peer: SocketAddr) {
+        self.orphans.remove_for_peer(peer);
+    }
+
+    fn try_add(
+        &mut self,
+        tx: Transaction,
+        utxos: &UTXOSet,
+        chain_height: u64,
+        median_time_past: i64,
     ) -> bool {
         // Coinbase not allowed in mempool
         if tx.inputs.is_empty() {
             return false;
         }
 
+        // Must be minable in the next block
+        if !is_final_transaction(&tx, chain_height, median_time_past) {
+            return false;
+        }
+
+        if is_standard(&tx, &self.policy).is_err() {
+            return false;
+        }
+
         let size = tx.serialized_size();
-        if size > MAX_TX_SIZE {
+
+        let txid = tx.txid();
+        if self.entries.contains_key(&txid) {
             return false;
         }
 
-        if !validate_transaction(&tx, utxos, chain_height) {
+        // Mempool entries spending the same outpoints
+        let conflicts: HashSet<Vec<u8>> = tx
+            .inputs
+            .iter()
+            .filter_map(|i| self.spent_outpoints.get(&(i.txid.clone(), i.index)))
+            .cloned()
+            .collect();
+
+        // Confirmed outputs plus outputs of mempool entries
+        let view = self.input_view(&tx, utxos, chain_height);
+
+        if !validate_transaction(&tx, &view, chain_height) {
             return false;
         }
 
-        // Prevent double-spend inside mempool
-        for input in &tx.inputs {
-            let key = (input.txid.clone(), input.index);
-            if self.spent_outpoints.contains(&key) {
+        let fee = match calculate_fee(&tx, &view) {
+            Some(f) if f > 0 && f >= self.policy.min_relay_fee(size) => f,
+            _ => return false,
+        };
+
+        // Ancestor / descendant limits
+        let parents: HashSet<Vec<u8>> = tx
+            .inputs
+            .iter()
+            .filter(|i| self.entries.contains_key(&i.txid))
+            .map(|i| i.txid.clone())
+            .collect();
+
+        let ancestors = self.ancestors_of(&parents);
+        let ancestor_size: usize = ancestors.iter().map(|a| self.entries[a].size).sum();
+        let ancestor_fee: i64 = ancestors.iter().map(|a| self.entries[a].fee).sum();
+
+        // Replace-by-fee
+        let evicted = if conflicts.is_empty() {
+            HashSet::new()
+        } else {
+            match self.check_replacement(&conflicts, &ancestors, fee, size) {
+                Some(e) => e,
+                None => return false,
+            }
+        };
+
+        if ancestors.len() + 1 > MAX_ANCESTORS || ancestor_size + size > MAX_ANCESTOR_SIZE {
+            return false;
+        }
+
+        for a in &ancestors {
+            let e = &self.entries[a];
+
+            // Room freed by replaced descendants
+            let freed: Vec<&MempoolEntry> = self
+                .descendants_of(a)
+                .iter()
+                .filter(|d| evicted.contains(*d))
+                .map(|d| &self.entries[d])
+                .collect();
+            let freed_size: usize = freed.iter().map(|d| d.size).sum();
+
+            if e.descendant_count - freed.len() + 1 > MAX_DESCENDANTS
+                || e.descendant_size - freed_size + size > MAX_DESCENDANT_SIZE
+            {
                 return false;
             }
         }
 
-        let fee = match calculate_fee(&tx, utxos) {
-            Some(f) if f > 0 => f,
-            _ => return false,
-        };
+        for id in &evicted {
+            self.remove_entry(id);
+        }
 
-        for input in &tx.inputs {
-            self.spent_outpoints
-                .insert((input.txid.clone(), input.index));
+        for a in &ancestors {
+            self.reindex(a, |e| {
+                e.descendant_count += 1;
+                e.descendant_size += size;
+                e.descendant_fee += fee;
+            });
         }
 
-        self.entries.push(MempoolEntry {
+        for p in &parents {
+            if let Some(e) = self.entries.get_mut(p) {
+                e.children.insert(txid.clone());
+            }
+        }
+
+        self.fee_estimator.track(&txid, fee, size, chain_height);
+
+        self.insert_entry(MempoolEntry {
+            txid: txid.clone(),
             tx,
             fee,
             size,
             timestamp: now(),
+            parents,
+            children: HashSet::new(),
+            ancestor_count: ancestors.len() + 1,
+            ancestor_size: ancestor_size + size,
+            ancestor_fee: ancestor_fee + fee,
+            descendant_count: 1,
+            descendant_size: size,
+            descendant_fee: fee,
         });
 
         // 🔒 MEMPOOL SIZE CAP + EVICTION (POLICY ONLY)
-        if self.entries.len() > MAX_MEMPOOL_TXS {
-            // Evict lowest fee-rate first
-            self.entries.sort_by(|a, b| {
-                let lhs = a.fee * b.size as i64;
-                let rhs = b.fee * a.size as i64;
-                lhs.cmp(&rhs)
-            });
+        // Evict lowest descendant fee-rate first,
+        // together with everything spending it
+        while self.entries.len() > MAX_MEMPOOL_TXS {
+            let lowest = match self.by_fee_rate.first() {
+                Some(key) => key.txid.clone(),
+                None => break,
+            };
+            self.remove_with_descendants(&lowest);
+        }
+
+        self.entries.contains_key(&txid)
+    }
+
+    /// Ancestor fee-rate packages for mining
+    ///
+    /// Each package is a transaction with its not-yet-selected
+    /// ancestors, parents first. Packages are ordered by
+    /// package fee-rate and never overlap.
+    pub fn mining_packages(&self) -> Vec<Vec<Transaction>> {
+        let mut scores: HashMap<Vec<u8>, (i64, usize)> = self
+            .entries
+            .iter()
+            .map(|(id, e)| (id.clone(), (e.ancestor_fee, e.ancestor_size)))
+            .collect();
+
+        let mut index: BTreeSet<FeeRateKey> = scores
+            .iter()
+            .map(|(id, (fee, size))| FeeRateKey { fee: *fee, size: *size, txid: id.clone() })
+            .collect();
+
+        let mut included: HashSet<Vec<u8>> = HashSet::new();
+        let mut packages = Vec::new();
+
+        while let Some(best) = index.pop_last() {
+            let top = &self.entries[&best.txid];
+
+            let mut package: Vec<&MempoolEntry> = self
+                .ancestors_of(&top.parents)
+                .into_iter()
+                .filter(|id| !included.contains(id))
+                .map(|id| &self.entries[&id])
+                .collect();
+            package.push(top);
+
+            // A parent always has fewer ancestors than its child
+            package.sort_by_key(|e| e.ancestor_count);
+
+            for e in &package {
+                included.insert(e.txid.clone());
+                let (fee, size) = scores[&e.txid];
+                index.remove(&FeeRateKey { fee, size, txid: e.txid.clone() });
+            }
+
+            // Remaining descendants no longer pay for these
+            for e in &package {
+                for d in self.descendants_of(&e.txid) {
+                    if included.contains(&d) {
+                        continue;
+                    }
+
+                    let score = scores.get_mut(&d).expect("descendant score");
+                    index.remove(&FeeRateKey { fee: score.0, size: score.1, txid: d.clone() });
+                    score.0 -= e.fee;
+                    score.1 -= e.size;
+                    index.insert(FeeRateKey { fee: score.0, size: score.1, txid: d });
+                }
+            }
 
-            self.entries.truncate(MAX_MEMPOOL_TXS);
-            self.rebuild_spent_outpoints();
+            packages.push(package.iter().map(|e| e.tx.clone()).collect());
         }
 
-        true
+        packages
     }
 
-    /// Transactions sorted by fee-rate for mining
-    pub fn sorted_for_mining(&self) -> Vec<Transaction> {
-        let mut entries = self.entries.clone();
+    /// Confirmed UTXO set as seen after every mempool entry
+    ///
+    /// Outputs spent by the mempool are removed and outputs it
+    /// creates are added, so wallets can chain payments.
+    pub fn utxo_view(&self, confirmed: &UTXOSet, chain_height: u64) -> UTXOSet {
+        let mut view = confirmed.clone();
 
-        entries.sort_by(|a, b| {
-            let lhs = a.fee * b.size as i64;
-            let rhs = b.fee * a.size as i64;
-            rhs.cmp(&lhs)
-        });
+        let mut ordered: Vec<&MempoolEntry> = self.entries.values().collect();
+        ordered.sort_by_key(|e| e.ancestor_count);
 
-        entries.into_iter().map(|e| e.tx).collect()
+        for e in ordered {
+            apply_transaction(&mut view, &e.tx, chain_height, false);
+        }
+
+        view
+    }
+
+    /// Drop entries (and their descendants) older than `max_age` seconds
+    pub fn expire(&mut self, max_age: i64) -> usize {
+        let cutoff = now() - max_age;
+        let expired: Vec<Vec<u8>> = self
+            .entries
+            .values()
+            .filter(|e| e.timestamp < cutoff)
+            .map(|e| e.txid.clone())
+            .collect();
+
+        let before = self.entries.len();
+        for txid in expired {
+            self.remove_with_descendants(&txid);
+        }
+        before - self.entries.len()
+    }
+
+    /// Write every entry to `path` (parents first)
+    pub fn save(&self, path: &Path) -> Result<(), &'static str> {
+        let mut ordered: Vec<&MempoolEntry> = self.entries.values().collect();
+        ordered.sort_by_key(|e| e.ancestor_count);
+
+        let file = MempoolFile {
+            version: MEMPOOL_FILE_VERSION,
+            entries: ordered
+                .into_iter()
+                .map(|e| PersistedEntry { tx: e.tx.clone(), timestamp: e.timestamp })
+                .collect(),
+        };
+
+        let data = bincode::serialize(&file).map_err(|_| "mempool encode failed")?;
+
+        // Write-then-rename so a crash never leaves a torn file
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data).map_err(|_| "mempool write failed")?;
+        fs::rename(&tmp, path).map_err(|_| "mempool write failed")?;
+
+        Ok(())
+    }
+
+    /// Reload entries saved by `save`
+    ///
+    /// Every entry is re-validated as a new submission against
+    /// the current UTXO set; expired entries are dropped.
+    /// Returns the number of entries accepted.
+    pub fn load(
+        &mut self,
+        path: &Path,
+        utxos: &UTXOSet,
+        chain_height: u64,
+        median_time_past: i64,
+    ) -> Result<usize, &'static str> {
+        if !path.exists() {
+            return Ok(0);
+        }
+
+        let data = fs::read(path).map_err(|_| "mempool read failed")?;
+        let file: MempoolFile = bincode::deserialize(&data).map_err(|_| "mempool file corrupt")?;
+
+        if file.version != MEMPOOL_FILE_VERSION {
+            return Err("unsupported mempool file version");
+        }
+
+        let cutoff = now() - MEMPOOL_EXPIRY_SECS;
+        let mut accepted = 0;
+
+        for entry in file.entries {
+            if entry.timestamp < cutoff {
+                continue;
+            }
+
+            let txid = entry.tx.txid();
+            if self.add_transaction(entry.tx, utxos, chain_height, median_time_past) {
+                // Keep the original age so expiry survives restarts
+                if let Some(e) = self.entries.get_mut(&txid) {
+                    e.timestamp = entry.timestamp;
+                }
+                accepted += 1;
+            }
+        }
+
+        Ok(accepted)
     }
 
     /// Remove confirmed transactions after block acceptance
     pub fn remove_confirmed(&mut self, confirmed: &[Transaction]) {
-        self.entries.retain(|entry| {
-            !confirmed
-                .iter()
-                .any(|tx| tx.txid() == entry.tx.txid())
-        });
+        for tx in confirmed {
+            self.remove_entry(&tx.txid());
+        }
+    }
+
+    /// Remove a connected block's transactions and every
+    /// entry double-spending them (with descendants)
+    pub fn remove_for_block(&mut self, block: &Block) {
+        self.fee_estimator.process_block(block);
+        self.remove_confirmed(&block.transactions);
+
+        for tx in &block.transactions {
+            for input in &tx.inputs {
+                let key = (input.txid.clone(), input.index);
+                if let Some(conflict) = self.spent_outpoints.get(&key).cloned() {
+                    self.remove_with_descendants(&conflict);
+                }
+            }
+        }
+    }
 
-        self.rebuild_spent_outpoints();
+    /// Drop entries no longer valid at the current tip
+    /// (missing inputs, immature coinbase spends, lock time)
+    pub fn revalidate(&mut self, utxos: &UTXOSet, chain_height: u64, median_time_past: i64) {
+        let mut ordered: Vec<(usize, Vec<u8>)> = self
+            .entries
+            .values()
+            .map(|e| (e.ancestor_count, e.txid.clone()))
+            .collect();
+        ordered.sort();
+
+        for (_, txid) in ordered {
+            let tx = match self.entries.get(&txid) {
+                Some(e) => e.tx.clone(),
+                None => continue, // removed with an invalid parent
+            };
+
+            let view = self.input_view(&tx, utxos, chain_height);
+
+            if !is_final_transaction(&tx, chain_height, median_time_past)
+                || !validate_transaction(&tx, &view, chain_height)
+            {
+                self.remove_with_descendants(&txid);
+            }
+        }
     }
 
     /// Re-add transactions from orphaned blocks
@@ -125,22 +622,261 @@ impl Mempool {
         orphaned: Vec<Block>,
         utxos: &UTXOSet,
         chain_height: u64,
+        median_time_past: i64,
     ) {
         for block in orphaned {
             for tx in block.transactions.into_iter().skip(1) {
-                let _ = self.add_transaction(tx, utxos, chain_height);
+                let _ = self.add_transaction(tx, utxos, chain_height, median_time_past);
+            }
+        }
+    }
+
+    /// UTXOs spent by `tx`, from the confirmed set or mempool
+    ///
+    /// Mempool outputs are treated as created in the next block.
+    pub fn input_view(&self, tx: &Transaction, utxos: &UTXOSet, chain_height: u64) -> UTXOSet {
+        let mut view = UTXOSet::new();
+
+        for input in &tx.inputs {
+            let key = outpoint_key(&input.txid, input.index);
+
+            if let Some(u) = utxos.get(&key) {
+                view.insert(key, u.clone());
+                continue;
+            }
+
+            let output = self
+                .entries
+                .get(&input.txid)
+                .and_then(|p| p.tx.outputs.get(input.index as usize));
+
+            if let Some(o) = output {
+                if !o.is_unspendable() {
+                    view.insert(key, UTXO {
+                        value: o.value,
+                        pubkey_hash: o.pubkey_hash.clone(),
+                        height: chain_height,
+                        is_coinbase: false,
+                        kind: o.kind.clone(),
+                    });
+                }
+            }
+        }
+
+        view
+    }
+
+    /// Parent txids whose outputs are in neither the UTXO set
+    /// nor the mempool
+    fn missing_parents(&self, tx: &Transaction, utxos: &UTXOSet) -> HashSet<Vec<u8>> {
+        tx.inputs
+            .iter()
+            .filter(|i| {
+                !utxos.contains_key(&outpoint_key(&i.txid, i.index))
+                    && !self.entries.contains_key(&i.txid)
+            })
+            .map(|i| i.txid.clone())
+            .collect()
+    }
+
+    /// Retry orphans of each txid in `parents`, cascading
+    /// through newly accepted orphans. Returns those accepted.
+    fn process_orphans(
+        &mut self,
+        mut parents: Vec<Vec<u8>>,
+        utxos: &UTXOSet,
+        chain_height: u64,
+        median_time_past: i64,
+    ) -> Vec<Transaction> {
+        let mut resolved = Vec::new();
+
+        while let Some(parent) = parents.pop() {
+            for orphan in self.orphans.take_children(&parent) {
+                let txid = orphan.tx.txid();
+
+                if self.try_add(orphan.tx.clone(), utxos, chain_height, median_time_past) {
+                    parents.push(txid);
+                    resolved.push(orphan.tx);
+                    continue;
+                }
+
+                // Still waiting on another parent
+                let missing = self.missing_parents(&orphan.tx, utxos);
+                if !missing.is_empty() {
+                    self.orphans.add(orphan.tx, orphan.peer, missing, orphan.received);
+                }
+            }
+        }
+
+        resolved
+    }
+
+    /// Replace-by-fee rules (POLICY ONLY)
+    ///
+    /// A replacement for `conflicts` must:
+    /// - evict at most MAX_REPLACEMENT_EVICTIONS transactions
+    ///   (conflicts plus their descendants)
+    /// - not spend outputs of anything it evicts
+    /// - pay a higher fee-rate than every direct conflict
+    /// - pay a higher absolute fee than everything evicted
+    /// - pay the difference at INCREMENTAL_RELAY_FEE_PER_BYTE for
+    ///   its own size plus the evicted descendants' size
+    ///
+    /// Returns the txids to evict.
+    fn check_replacement(
+        &self,
+        conflicts: &HashSet<Vec<u8>>,
+        ancestors: &HashSet<Vec<u8>>,
+        fee: i64,
+        size: usize,
+    ) -> Option<HashSet<Vec<u8>>> {
+        let mut evicted = HashSet::new();
+        for c in conflicts {
+            evicted.insert(c.clone());
+            evicted.extend(self.descendants_of(c));
+        }
+
+        if evicted.len() > MAX_REPLACEMENT_EVICTIONS {
+            return None;
+        }
+
+        if ancestors.iter().any(|a| evicted.contains(a)) {
+            return None;
+        }
+
+        for c in conflicts {
+            let e = &self.entries[c];
+            if fee as i128 * e.size as i128 <= e.fee as i128 * size as i128 {
+                return None;
+            }
+        }
+
+        let evicted_fee: i64 = evicted.iter().map(|id| self.entries[id].fee).sum();
+        if fee <= evicted_fee {
+            return None;
+        }
+
+        let descendant_size: usize = evicted
+            .iter()
+            .filter(|id| !conflicts.contains(*id))
+            .map(|id| self.entries[id].size)
+            .sum();
+
+        if fee - evicted_fee < INCREMENTAL_RELAY_FEE_PER_BYTE * (size + descendant_size) as i64 {
+            return None;
+        }
+
+        Some(evicted)
+    }
+
+    /// All in-mempool ancestors reachable from `parents`
+    fn ancestors_of(&self, parents: &HashSet<Vec<u8>>) -> HashSet<Vec<u8>> {
+        let mut found = HashSet::new();
+        let mut stack: Vec<Vec<u8>> = parents.iter().cloned().collect();
+
+        while let Some(id) = stack.pop() {
+            if let Some(e) = self.entries.get(&id) {
+                if found.insert(id) {
+                    stack.extend(e.parents.iter().cloned());
+                }
+            }
+        }
+
+        found
+    }
+
+    /// All in-mempool descendants of `txid` (excluding itself)
+    fn descendants_of(&self, txid: &[u8]) -> HashSet<Vec<u8>> {
+        let mut found = HashSet::new();
+        let mut stack: Vec<Vec<u8>> = match self.entries.get(txid) {
+            Some(e) => e.children.iter().cloned().collect(),
+            None => return found,
+        };
+
+        while let Some(id) = stack.pop() {
+            if let Some(e) = self.entries.get(&id) {
+                if found.insert(id) {
+                    stack.extend(e.children.iter().cloned());
+                }
             }
         }
+
+        found
+    }
+
+    /// Update an entry, keeping the fee-rate index in sync
+    fn reindex(&mut self, txid: &[u8], update: impl FnOnce(&mut MempoolEntry)) {
+        if let Some(e) = self.entries.get_mut(txid) {
+            self.by_fee_rate.remove(&FeeRateKey::of(e));
+            update(e);
+            self.by_fee_rate.insert(FeeRateKey::of(e));
+        }
+    }
+
+    fn insert_entry(&mut self, entry: MempoolEntry) {
+        for input in &entry.tx.inputs {
+            self.spent_outpoints
+                .insert((input.txid.clone(), input.index), entry.txid.clone());
+        }
+
+        self.by_fee_rate.insert(FeeRateKey::of(&entry));
+        self.entries.insert(entry.txid.clone(), entry);
     }
 
-    fn rebuild_spent_outpoints(&mut self) {
-        self.spent_outpoints.clear();
-        for entry in &self.entries {
-            for input in &entry.tx.inputs {
-                self.spent_outpoints
-                    .insert((input.txid.clone(), input.index));
+    /// Remove one entry, updating its relatives' package totals
+    fn remove_entry(&mut self, txid: &[u8]) -> Option<MempoolEntry> {
+        let (ancestors, descendants) = match self.entries.get(txid) {
+            Some(e) => (self.ancestors_of(&e.parents), self.descendants_of(txid)),
+            None => return None,
+        };
+
+        let entry = self.entries.remove(txid)?;
+        self.by_fee_rate.remove(&FeeRateKey::of(&entry));
+        self.fee_estimator.forget(txid);
+
+        for a in &ancestors {
+            self.reindex(a, |e| {
+                e.descendant_count -= 1;
+                e.descendant_size -= entry.size;
+                e.descendant_fee -= entry.fee;
+            });
+        }
+
+        for d in &descendants {
+            if let Some(e) = self.entries.get_mut(d) {
+                e.ancestor_count -= 1;
+                e.ancestor_size -= entry.size;
+                e.ancestor_fee -= entry.fee;
             }
         }
+
+        for p in &entry.parents {
+            if let Some(e) = self.entries.get_mut(p) {
+                e.children.remove(txid);
+            }
+        }
+
+        for c in &entry.children {
+            if let Some(e) = self.entries.get_mut(c) {
+                e.parents.remove(txid);
+            }
+        }
+
+        for input in &entry.tx.inputs {
+            self.spent_outpoints.remove(&(input.txid.clone(), input.index));
+        }
+
+        Some(entry)
+    }
+
+    /// Remove an entry and everything spending it
+    fn remove_with_descendants(&mut self, txid: &[u8]) {
+        let mut doomed: Vec<Vec<u8>> = self.descendants_of(txid).into_iter().collect();
+        doomed.push(txid.to_vec());
+
+        for id in doomed {
+            self.remove_entry(&id);
+        }
     }
 }
 
@@ -171,3 +907,407 @@ fn now() -> i64 {
         .expect("system time")
         .as_secs() as i64
 }
+
+/// Keep the mempool in step with the active chain
+///
+/// Runs until the chain drops its sender. Each batch of
+/// events is applied under the mempool lock, then the chain
+/// lock (the node-wide lock order):
+/// 1. connected blocks: confirmed txs and conflicts removed
+/// 2. disconnected blocks: txs re-submitted, oldest first
+/// 3. remaining entries re-validated against the new tip
+pub fn run_chain_sync(
+    events: Receiver<ChainEvent>,
+    chain: Arc<Mutex<Blockchain>>,
+    mempool: Arc<Mutex<Mempool>>,
+) {
+    while let Ok(first) = events.recv() {
+        let mut connected = Vec::new();
+        let mut disconnected = Vec::new();
+
+        for event in std::iter::once(first).chain(events.try_iter()) {
+            match event {
+                ChainEvent::BlockConnected(b) => connected.push(b),
+                ChainEvent::BlockDisconnected(b) => disconnected.push(b),
+            }
+        }
+
+        let mut m = mempool.lock().unwrap();
+        let c = chain.lock().unwrap();
+        let height = c.height();
+        let mtp = median_time_past(&c.blocks);
+
+        for block in &connected {
+            m.remove_for_block(block);
+        }
+
+        for block in &connected {
+            m.retry_orphans_for_block(block, &c.utxos, height, mtp);
+        }
+
+        if !disconnected.is_empty() {
+            disconnected.sort_by_key(|b| b.header.height);
+            m.resurrect_from_orphans(disconnected, &c.utxos, height, mtp);
+            m.revalidate(&c.utxos, height, mtp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BlockHeader;
+    use crate::consensus::params::MAX_TARGET;
+    use crate::consensus::version::CONSENSUS_V6_HEIGHT;
+    use crate::crypto::{public_key, pubkey_hash, secret_key_from_seed, sign};
+    use crate::transaction::{OutputKind, TxInput, TxOutput, TX_VERSION_LOCKTIME};
+
+    const HEIGHT: u64 = CONSENSUS_V6_HEIGHT;
+    const FUNDING: u64 = 100_000;
+
+    fn owner() -> Vec<u8> {
+        pubkey_hash(&public_key(&secret_key_from_seed(&[7u8; 32])))
+    }
+
+    /// Confirmed outputs `[i; 32]:0` of FUNDING each
+    fn funded(count: u8) -> UTXOSet {
+        (1..=count)
+            .map(|i| {
+                (outpoint_key(&[i; 32], 0), UTXO {
+                    value: FUNDING,
+                    pubkey_hash: owner(),
+                    height: 1,
+                    is_coinbase: false,
+                    kind: OutputKind::PubkeyHash,
+                })
+            })
+            .collect()
+    }
+
+    fn confirmed(i: u8) -> (Vec<u8>, u32) {
+        (vec![i; 32], 0)
+    }
+
+    /// Signed version 2 spend of `prevouts` paying `outputs`
+    fn spend(prevouts: &[(Vec<u8>, u32)], outputs: &[u64]) -> Transaction {
+        let sk = secret_key_from_seed(&[7u8; 32]);
+
+        let mut tx = Transaction {
+            version: TX_VERSION_LOCKTIME,
+            inputs: prevouts
+                .iter()
+                .map(|(txid, index)| TxInput {
+                    txid: txid.clone(),
+                    index: *index,
+                    pubkey: Vec::new(),
+                    signature: Vec::new(),
+                    address_index: 0,
+                    signatures: Vec::new(),
+                    preimage: Vec::new(),
+                })
+                .collect(),
+            outputs: outputs
+                .iter()
+                .map(|v| TxOutput::pay_to_pubkey_hash(*v, owner()))
+                .collect(),
+            lock_time: 0,
+        };
+
+        let sighash = tx.sighash();
+        for input in &mut tx.inputs {
+            input.pubkey = public_key(&sk).serialize().to_vec();
+            input.signature = sign(&sighash, &sk);
+        }
+        tx
+    }
+
+    fn add(pool: &mut Mempool, tx: &Transaction, utxos: &UTXOSet) -> bool {
+        pool.add_transaction(tx.clone(), utxos, HEIGHT, 0)
+    }
+
+    fn txids(packages: &[Vec<Transaction>]) -> Vec<Vec<Vec<u8>>> {
+        packages
+            .iter()
+            .map(|p| p.iter().map(|tx| tx.txid()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn fee_rate_index_orders_lowest_first() {
+        let utxos = funded(2);
+        let mut pool = Mempool::new();
+
+        let high = spend(&[confirmed(1)], &[FUNDING - 5_000]);
+        let low = spend(&[confirmed(2)], &[FUNDING - 1_000]);
+        assert!(add(&mut pool, &high, &utxos));
+        assert!(add(&mut pool, &low, &utxos));
+
+        assert_eq!(pool.get(&high.txid()).unwrap().fee, 5_000);
+        assert_eq!(pool.by_fee_rate.first().unwrap().txid, low.txid());
+        assert_eq!(pool.by_fee_rate.last().unwrap().txid, high.txid());
+    }
+
+    #[test]
+    fn confirmed_entries_leave_every_index() {
+        let utxos = funded(1);
+        let mut pool = Mempool::new();
+
+        let tx = spend(&[confirmed(1)], &[FUNDING - 1_000]);
+        assert!(add(&mut pool, &tx, &utxos));
+        assert_eq!(pool.spent_outpoints.get(&confirmed(1)), Some(&tx.txid()));
+
+        pool.remove_confirmed(std::slice::from_ref(&tx));
+        assert_eq!(pool.size(), 0);
+        assert!(pool.by_fee_rate.is_empty());
+        assert!(pool.spent_outpoints.is_empty());
+    }
+
+    #[test]
+    fn packages_ordered_by_fee_rate() {
+        let utxos = funded(2);
+        let mut pool = Mempool::new();
+
+        let low = spend(&[confirmed(1)], &[FUNDING - 1_000]);
+        let high = spend(&[confirmed(2)], &[FUNDING - 5_000]);
+        assert!(add(&mut pool, &low, &utxos));
+        assert!(add(&mut pool, &high, &utxos));
+
+        assert_eq!(
+            txids(&pool.mining_packages()),
+            vec![vec![high.txid()], vec![low.txid()]]
+        );
+    }
+
+    #[test]
+    fn child_pays_for_parent() {
+        let utxos = funded(2);
+        let mut pool = Mempool::new();
+
+        let parent = spend(&[confirmed(1)], &[FUNDING - 200]);
+        let child = spend(&[(parent.txid(), 0)], &[FUNDING - 200 - 5_000]);
+        let other = spend(&[confirmed(2)], &[FUNDING - 2_000]);
+
+        assert!(add(&mut pool, &parent, &utxos));
+        assert!(add(&mut pool, &child, &utxos));
+        assert!(add(&mut pool, &other, &utxos));
+
+        let entry = pool.get(&child.txid()).unwrap();
+        assert_eq!(entry.ancestor_count, 2);
+        assert_eq!(entry.ancestor_fee, 5_200);
+        assert_eq!(pool.get(&parent.txid()).unwrap().descendant_count, 2);
+
+        // Parent first, pulled in by its child's fee
+        assert_eq!(
+            txids(&pool.mining_packages()),
+            vec![vec![parent.txid(), child.txid()], vec![other.txid()]]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_parent() {
+        let utxos = funded(1);
+        let mut pool = Mempool::new();
+
+        let orphan = spend(&[(vec![9u8; 32], 0)], &[FUNDING - 1_000]);
+        assert!(!add(&mut pool, &orphan, &utxos));
+        assert_eq!(pool.size(), 0);
+    }
+
+    #[test]
+    fn replacement_must_pay_more() {
+        let utxos = funded(1);
+        let mut pool = Mempool::new();
+
+        let original = spend(&[confirmed(1)], &[FUNDING - 1_000]);
+        assert!(add(&mut pool, &original, &utxos));
+
+        // Same fee, but larger: lower fee-rate
+        let larger = spend(&[confirmed(1)], &[50_000, FUNDING - 51_000]);
+        assert!(!add(&mut pool, &larger, &utxos));
+
+        // Higher, but the difference does not cover its own relay
+        let short = spend(&[confirmed(1)], &[FUNDING - 1_100]);
+        assert!(!add(&mut pool, &short, &utxos));
+
+        let better = spend(&[confirmed(1)], &[FUNDING - 1_300]);
+        assert!(add(&mut pool, &better, &utxos));
+        assert!(!pool.contains(&original.txid()));
+        assert_eq!(pool.size(), 1);
+    }
+
+    #[test]
+    fn replacement_pays_for_evicted_descendants() {
+        let utxos = funded(1);
+        let mut pool = Mempool::new();
+
+        let parent = spend(&[confirmed(1)], &[FUNDING - 1_000]);
+        let child = spend(&[(parent.txid(), 0)], &[FUNDING - 2_000]);
+        assert!(add(&mut pool, &parent, &utxos));
+        assert!(add(&mut pool, &child, &utxos));
+
+        let conflicts: HashSet<Vec<u8>> = [parent.txid()].into_iter().collect();
+        let size = parent.serialized_size();
+
+        // Evicts both; 300 over 2_000 is short of 2 × 192 bytes
+        assert!(pool
+            .check_replacement(&conflicts, &HashSet::new(), 2_300, size)
+            .is_none());
+
+        let evicted = pool
+            .check_replacement(&conflicts, &HashSet::new(), 2_400, size)
+            .unwrap();
+        assert_eq!(evicted, [parent.txid(), child.txid()].into_iter().collect());
+
+        let replacement = spend(&[confirmed(1)], &[FUNDING - 2_400]);
+        assert!(add(&mut pool, &replacement, &utxos));
+        assert!(!pool.contains(&parent.txid()));
+        assert!(!pool.contains(&child.txid()));
+    }
+
+    #[test]
+    fn replacement_may_not_spend_what_it_evicts() {
+        let utxos = funded(1);
+        let mut pool = Mempool::new();
+
+        let parent = spend(&[confirmed(1)], &[FUNDING - 1_000]);
+        assert!(add(&mut pool, &parent, &utxos));
+
+        let conflicts: HashSet<Vec<u8>> = [parent.txid()].into_iter().collect();
+        assert!(pool
+            .check_replacement(&conflicts, &conflicts, 50_000, 400)
+            .is_none());
+
+        // Double-spends the parent's input while spending its output
+        let both = spend(&[confirmed(1), (parent.txid(), 0)], &[FUNDING]);
+        assert!(!add(&mut pool, &both, &utxos));
+        assert!(pool.contains(&parent.txid()));
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let utxos = funded(1);
+        let mut pool = Mempool::new();
+
+        let parent = spend(&[confirmed(1)], &[FUNDING - 1_000]);
+        let child = spend(&[(parent.txid(), 0)], &[FUNDING - 2_000]);
+        assert!(add(&mut pool, &parent, &utxos));
+        assert!(add(&mut pool, &child, &utxos));
+
+        let path = std::env::temp_dir().join(format!("mempool-test-{}.dat", std::process::id()));
+        pool.save(&path).unwrap();
+
+        let mut loaded = Mempool::new();
+        let accepted = loaded.load(&path, &utxos, HEIGHT, 0);
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(accepted, Ok(2));
+        assert!(loaded.contains(&parent.txid()));
+        assert_eq!(loaded.get(&child.txid()).unwrap().ancestor_count, 2);
+    }
+
+    #[test]
+    fn reorg_resubmits_and_revalidates() {
+        let utxos = funded(1);
+        let mut pool = Mempool::new();
+
+        let tx = spend(&[confirmed(1)], &[FUNDING - 1_000]);
+        assert!(add(&mut pool, &tx, &utxos));
+
+        let coinbase = Transaction::new(
+            Vec::new(),
+            vec![TxOutput::pay_to_pubkey_hash(50, owner())],
+        );
+        let block = Block {
+            header: BlockHeader {
+                version: 0,
+                height: HEIGHT,
+                timestamp: 0,
+                prev_hash: vec![0u8; 32],
+                nonce: 0,
+                target: MAX_TARGET,
+                merkle_root: Vec::new(),
+            },
+            transactions: vec![coinbase, tx.clone()],
+            hash: vec![1u8; 32],
+        };
+
+        // Connected: confirmed, leaves the pool
+        pool.remove_for_block(&block);
+        assert_eq!(pool.size(), 0);
+
+        // Disconnected: back in the pool
+        pool.resurrect_from_orphans(vec![block], &utxos, HEIGHT, 0);
+        assert!(pool.contains(&tx.txid()));
+
+        // New tip spent its input elsewhere
+        pool.revalidate(&UTXOSet::new(), HEIGHT, 0);
+        assert_eq!(pool.size(), 0);
+    }
+
+    #[test]
+    fn orphan_resolved_when_parent_enters_mempool() {
+        let utxos = funded(1);
+        let mut pool = Mempool::new();
+
+        let parent = spend(&[confirmed(1)], &[FUNDING - 1_000]);
+        let child = spend(&[(parent.txid(), 0)], &[FUNDING - 2_000]);
+
+        assert!(matches!(
+            pool.accept_from_peer(child.clone(), None, &utxos, HEIGHT, 0),
+            TxAcceptance::Orphaned
+        ));
+        assert_eq!(pool.orphan_count(), 1);
+
+        match pool.accept_from_peer(parent, None, &utxos, HEIGHT, 0) {
+            TxAcceptance::Accepted { resolved } => {
+                assert_eq!(resolved.len(), 1);
+                assert_eq!(resolved[0].txid(), child.txid());
+            }
+            _ => panic!("parent not accepted"),
+        }
+        assert!(pool.contains(&child.txid()));
+        assert_eq!(pool.orphan_count(), 0);
+    }
+
+    #[test]
+    fn orphan_resolved_when_parent_is_mined() {
+        let utxos = funded(1);
+        let mut pool = Mempool::new();
+
+        let parent = spend(&[confirmed(1)], &[FUNDING - 1_000]);
+        let child = spend(&[(parent.txid(), 0)], &[FUNDING - 2_000]);
+
+        assert!(matches!(
+            pool.accept_from_peer(child.clone(), None, &utxos, HEIGHT, 0),
+            TxAcceptance::Orphaned
+        ));
+
+        // Parent confirmed without passing through the mempool
+        let block = Block {
+            header: BlockHeader {
+                version: 0,
+                height: HEIGHT,
+                timestamp: 0,
+                prev_hash: vec![0u8; 32],
+                nonce: 0,
+                target: MAX_TARGET,
+                merkle_root: Vec::new(),
+            },
+            transactions: vec![parent.clone()],
+            hash: vec![1u8; 32],
+        };
+        let mut after = UTXOSet::new();
+        after.insert(outpoint_key(&parent.txid(), 0), UTXO {
+            value: FUNDING - 1_000,
+            pubkey_hash: owner(),
+            height: HEIGHT,
+            is_coinbase: false,
+            kind: OutputKind::PubkeyHash,
+        });
+
+        let resolved = pool.retry_orphans_for_block(&block, &after, HEIGHT + 1, 0);
+        assert_eq!(resolved.len(), 1);
+        assert!(pool.contains(&child.txid()));
+        assert_eq!(pool.orphan_count(), 0);
+    }
+}
@@ -1,16 +1,80 @@
 use serde::{Serialize, Deserialize};
-use crate::core::block::Block;
+use crate::core::block::{Block, BlockHeader};
 use crate::core::transaction::Transaction;
+use crate::node::transport::frame;
 
-pub const PROTOCOL_VERSION: u32 = 3;
+pub const PROTOCOL_VERSION: u32 = 9; // v9: Listen
+
+/// Oldest protocol version we talk to (first with this Hello layout)
+///
+/// Messages newer than a peer's negotiated version are neither
+/// sent to it nor accepted from it (`NetworkMessage::min_version`).
+pub const MIN_PROTOCOL_VERSION: u32 = 8;
+
+// ───── Service flags (Hello.services) ─────
+
+/// Serves full blocks and headers
+pub const NODE_NETWORK: u64 = 1 << 0;
+
+/// Services this node offers
+pub const LOCAL_SERVICES: u64 = NODE_NETWORK;
+
+/// Sent in Hello.agent
+pub const USER_AGENT: &str = concat!("/revelation:", env!("CARGO_PKG_VERSION"), "/");
+
+/// Most items in one Inv / GetData / NotFound
+pub const MAX_INV_ITEMS: usize = 50_000;
+
+/// Most blocks served for one GetData
+///
+/// Replies are queued per peer (PEER_SEND_QUEUE frames); a peer
+/// asking for more gets the first ones and requests the rest again.
+pub const MAX_BLOCKS_PER_GETDATA: usize = 16;
+
+/// Most objects (blocks and transactions) served for one GetData
+pub const MAX_GETDATA_REPLIES: usize = 128;
+
+/// Most headers in one Headers reply
+pub const MAX_HEADERS: usize = 2_000;
+
+/// Most hashes accepted in a GetHeaders locator
+pub const MAX_LOCATOR_HASHES: usize = 101;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InvKind {
+    Block,
+    Transaction,
+}
+
+/// Announced object: block hash or txid
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct InvItem {
+    pub kind: InvKind,
+    pub hash: Vec<u8>,
+}
+
+impl InvItem {
+    pub fn block(hash: &[u8]) -> Self {
+        Self { kind: InvKind::Block, hash: hash.to_vec() }
+    }
+
+    pub fn transaction(txid: &[u8]) -> Self {
+        Self { kind: InvKind::Transaction, hash: txid.to_vec() }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum NetworkMessage {
-    /// Initial handshake
+    /// Initial handshake, sent by both sides on connect
     Hello {
         version: u32,
+        /// Service flags (NODE_NETWORK, ...)
+        services: u64,
         height: u64,
         agent: String,
+        /// Chain identity: peers on another chain are dropped
+        genesis_hash: Vec<u8>,
+        magic: [u8; 4],
     },
 
     /// Ask peer for known addresses
@@ -20,6 +84,9 @@ pub enum NetworkMessage {
     Addr(Vec<String>),
 
     /// Request blocks from height
+    ///
+    /// Legacy (pre-v6): assumes shared history up to that
+    /// height. Superseded by GetHeaders.
     SyncRequest {
         from_height: u64,
     },
@@ -33,4 +100,96 @@ pub enum NetworkMessage {
     /// Keepalive
     Ping,
     Pong,
+
+    // ───── v5: inventory (appended; bincode tags are positional) ─────
+
+    /// "I have these" — peers fetch what they lack with GetData
+    Inv(Vec<InvItem>),
+
+    /// Request full objects previously announced
+    GetData(Vec<InvItem>),
+
+    /// Requested objects we no longer have
+    NotFound(Vec<InvItem>),
+
+    // ───── v6: headers-first sync ─────
+
+    /// Request headers after the first locator hash the peer
+    /// shares with us, up to `stop` or MAX_HEADERS
+    GetHeaders {
+        locator: Vec<Vec<u8>>,
+        stop: Option<Vec<u8>>,
+    },
+
+    /// Consecutive headers, oldest first
+    Headers(Vec<BlockHeader>),
+
+    // ───── v7: handshake ─────
+
+    /// Acknowledges the peer's Hello
+    VerAck,
+
+    // ───── v9: listen address ─────
+
+    /// Port the sender accepts connections on (its IP is the
+    /// connection's), sent after the handshake
+    Listen {
+        port: u16,
+    },
+}
+
+impl NetworkMessage {
+    /// Frame command name (at most 12 ASCII bytes)
+    pub fn command(&self) -> &'static str {
+        match self {
+            NetworkMessage::Hello { .. } => "hello",
+            NetworkMessage::GetAddr => "getaddr",
+            NetworkMessage::Addr(_) => "addr",
+            NetworkMessage::SyncRequest { .. } => "syncrequest",
+            NetworkMessage::Block(_) => "block",
+            NetworkMessage::Transaction(_) => "tx",
+            NetworkMessage::Ping => "ping",
+            NetworkMessage::Pong => "pong",
+            NetworkMessage::Inv(_) => "inv",
+            NetworkMessage::GetData(_) => "getdata",
+            NetworkMessage::NotFound(_) => "notfound",
+            NetworkMessage::GetHeaders { .. } => "getheaders",
+            NetworkMessage::Headers(_) => "headers",
+            NetworkMessage::VerAck => "verack",
+            NetworkMessage::Listen { .. } => "listen",
+        }
+    }
+
+    /// Protocol version that introduced this message
+    pub fn min_version(&self) -> u32 {
+        match self {
+            NetworkMessage::Inv(_)
+            | NetworkMessage::GetData(_)
+            | NetworkMessage::NotFound(_) => 5,
+            NetworkMessage::GetHeaders { .. } | NetworkMessage::Headers(_) => 6,
+            NetworkMessage::VerAck => 7,
+            NetworkMessage::Hello { .. } => 8,
+            NetworkMessage::Listen { .. } => 9,
+            _ => 1,
+        }
+    }
+
+    /// Serialize into a wire frame
+    pub fn to_frame(&self) -> Option<Vec<u8>> {
+        bincode::serialize(self)
+            .ok()
+            .map(|payload| frame::encode(self.command(), &payload))
+    }
+
+    /// Parse one wire frame
+    pub fn from_frame(data: &[u8]) -> Result<Self, &'static str> {
+        let f = frame::decode(data)?;
+        let msg: NetworkMessage =
+            bincode::deserialize(&f.payload).map_err(|_| "undecodable payload")?;
+
+        if msg.command() != f.command {
+            return Err("command mismatch");
+        }
+        Ok(msg)
+    }
 }
@@ -12,96 +12,158 @@ use time::OffsetDateTime;
 
 use crate::core::block::{Block, BlockHeader};
 use crate::core::transaction::{Transaction, TxOutput};
-use crate::core::utxo::UTXOSet;
+use crate::core::utxo::{UTXOSet, UTXO, outpoint_key, apply_transaction};
 
 use crate::reward::block_reward;
 use crate::consensus::difficulty::{
     calculate_next_target,
     calculate_next_target_v5,
 };
+use crate::consensus::deployment::compute_block_version;
 use crate::consensus::version::{
     consensus_version_for_height,
     ConsensusVersion,
 };
 use crate::merkle::merkle_root;
 use crate::pow::mine;
-use crate::validation::validate_transaction;
-use crate::policy::{MAX_BLOCK_TXS, MAX_BLOCK_TX_BYTES};
+use crate::core::chain::median_time_past;
+use crate::validation::{validate_transaction, is_final_transaction};
+use crate::policy::{MAX_BLOCK_TXS, MAX_BLOCK_TX_BYTES, PolicyConfig, is_standard};
+
+/// Spent and created UTXOs, for rolling back a failed package
+#[derive(Default)]
+struct Undo {
+    spent: Vec<(String, UTXO)>,
+    created: Vec<String>,
+}
+
+impl Undo {
+    fn record(&mut self, view: &UTXOSet, tx: &Transaction) {
+        for i in &tx.inputs {
+            let key = outpoint_key(&i.txid, i.index);
+            if let Some(u) = view.get(&key) {
+                self.spent.push((key, u.clone()));
+            }
+        }
+
+        let txid = tx.txid();
+        for (index, o) in tx.outputs.iter().enumerate() {
+            if !o.is_unspendable() {
+                self.created.push(outpoint_key(&txid, index as u32));
+            }
+        }
+    }
+
+    fn rollback(self, view: &mut UTXOSet) {
+        for key in self.created {
+            view.remove(&key);
+        }
+        for (key, u) in self.spent {
+            view.insert(key, u);
+        }
+    }
+}
+
+/// Validate and apply a package (parents first) to the running view
+///
+/// Returns (fee, size, undo). On any failure the view is left unchanged.
+fn apply_package(
+    view: &mut UTXOSet,
+    package: &[Transaction],
+    height: u64,
+    mtp: i64,
+    policy: &PolicyConfig,
+) -> Option<(i64, usize, Undo)> {
+    let mut undo = Undo::default();
+    let mut fee = 0i64;
+    let mut size = 0usize;
+
+    for tx in package {
+        let valid = is_standard(tx, policy).is_ok()
+            && is_final_transaction(tx, height, mtp)
+            && validate_transaction(tx, view, height);
+
+        if !valid {
+            undo.rollback(view);
+            return None;
+        }
+
+        let input_sum: i64 = tx
+            .inputs
+            .iter()
+            .filter_map(|i| view.get(&outpoint_key(&i.txid, i.index)))
+            .map(|u| u.value as i64)
+            .sum();
+        let output_sum: i64 = tx.outputs.iter().map(|o| o.value as i64).sum();
 
-const MIN_FEE_PER_BYTE: i64 = 1; // POLICY ONLY
+        fee += input_sum - output_sum;
+        size += tx.serialized_size();
 
+        undo.record(view, tx);
+        apply_transaction(view, tx, height, false);
+    }
+
+    Some((fee, size, undo))
+}
+
+/// Build and mine a block
+///
+/// `packages` come from `Mempool::mining_packages`: each is
+/// a transaction with its unconfirmed ancestors, parents
+/// first, best package fee-rate first. Packages must be
+/// standard under `policy` and pay its minimum fee rate.
 pub fn mine_block(
     prev_block: &Block,
     utxos: &UTXOSet,
-    mempool_txs: Vec<Transaction>,
+    packages: Vec<Vec<Transaction>>,
     miner_pubkey_hash: Vec<u8>,
     chain: &[Block],
+    policy: &PolicyConfig,
 ) -> Block {
     let height = prev_block.header.height + 1;
     let version = consensus_version_for_height(height);
+    let mtp = median_time_past(chain);
 
     let mut selected: Vec<Transaction> = Vec::new();
     let mut total_fees: u64 = 0;
     let mut total_bytes: usize = 0;
 
-    // ───────── Select transactions ─────────
-    for tx in mempool_txs {
-        if selected.len() >= MAX_BLOCK_TXS {
-            break;
-        }
+    // Spends may depend on earlier selected transactions
+    let mut view = utxos.clone();
 
-        let size = tx.serialized_size();
-        if total_bytes + size > MAX_BLOCK_TX_BYTES {
-            break;
-        }
-
-        if !validate_transaction(&tx, utxos, height) {
+    // ───────── Select packages ─────────
+    for package in packages {
+        if selected.len() + package.len() > MAX_BLOCK_TXS {
             continue;
         }
 
-        let mut input_sum = 0i64;
-        let mut output_sum = 0i64;
-
-        for i in &tx.inputs {
-            let key = format!(
-                "{}:{}",
-                hex::encode(&i.txid),
-                i.index
-            );
-            if let Some(u) = utxos.get(&key) {
-                input_sum += u.value as i64;
-            }
-        }
-
-        for o in &tx.outputs {
-            output_sum += o.value as i64;
-        }
-
-        let fee = input_sum - output_sum;
-        if fee <= 0 {
+        let package_size: usize = package.iter().map(|tx| tx.serialized_size()).sum();
+        if total_bytes + package_size > MAX_BLOCK_TX_BYTES {
             continue;
         }
 
-        let fee_rate = fee / size as i64;
-        if fee_rate < MIN_FEE_PER_BYTE {
+        let (fee, size, undo) = match apply_package(&mut view, &package, height, mtp, policy) {
+            Some(r) => r,
+            None => continue,
+        };
+
+        if fee <= 0 || fee < policy.min_relay_fee(size) {
+            undo.rollback(&mut view);
             continue;
         }
 
         total_fees += fee as u64;
         total_bytes += size;
-        selected.push(tx);
+        selected.extend(package);
     }
 
     // ───────── Coinbase transaction ─────────
     let coinbase_value = block_reward(height) + total_fees;
 
-    let coinbase = Transaction {
-        inputs: vec![],
-        outputs: vec![TxOutput {
-            value: coinbase_value,
-            pubkey_hash: miner_pubkey_hash,
-        }],
-    };
+    let coinbase = Transaction::new(
+        vec![],
+        vec![TxOutput::pay_to_pubkey_hash(coinbase_value, miner_pubkey_hash)],
+    );
 
     let mut txs = Vec::with_capacity(selected.len() + 1);
     txs.push(coinbase);
@@ -110,11 +172,14 @@ pub fn mine_block(
     // ───────── Difficulty target ─────────
     let target = match version {
         ConsensusVersion::V4 => calculate_next_target(chain),
-        ConsensusVersion::V5 => calculate_next_target_v5(chain, height),
+        ConsensusVersion::V5 | ConsensusVersion::V6 => {
+            calculate_next_target_v5(chain, height)
+        }
     };
 
     let mut block = Block {
         header: BlockHeader {
+            version: compute_block_version(chain, height),
             height,
             timestamp: OffsetDateTime::now_utc().unix_timestamp(),
             prev_hash: prev_block.hash.clone(),
@@ -2,6 +2,12 @@ pub mod message;
 pub mod p2p;
 pub mod miner;
 pub mod mempool;
+pub mod fee_estimator;
+pub mod orphans;
+pub mod inventory;
+pub mod sync;
+pub mod peer;
+pub mod addrman;
 pub mod dedup;
 
 // transport is a submodule directory
@@ -0,0 +1,257 @@
+// ─────────────────────────────────────────────
+// ORPHAN TRANSACTIONS (POLICY ONLY)
+//
+// Transactions whose inputs reference outputs we
+// have not seen yet. Relays over satellite, BLE and
+// store-and-forward links arrive in any order, so a
+// child may come before its parent.
+//
+// Orphans are indexed by missing parent txid and
+// retried when that parent enters the mempool or
+// a block.
+// ─────────────────────────────────────────────
+
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+
+use crate::core::transaction::Transaction;
+use crate::policy::{MAX_ORPHAN_TXS, MAX_ORPHANS_PER_PEER, ORPHAN_EXPIRY_SECS};
+
+pub struct Orphan {
+    pub tx: Transaction,
+    /// Relaying peer (None = local submission)
+    pub peer: Option<SocketAddr>,
+    pub received: i64,
+    missing_parents: HashSet<Vec<u8>>,
+}
+
+#[derive(Default)]
+pub struct OrphanPool {
+    orphans: HashMap<Vec<u8>, Orphan>,
+    by_parent: HashMap<Vec<u8>, HashSet<Vec<u8>>>,
+    per_peer: HashMap<SocketAddr, usize>,
+}
+
+impl OrphanPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.orphans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orphans.is_empty()
+    }
+
+    pub fn contains(&self, txid: &[u8]) -> bool {
+        self.orphans.contains_key(txid)
+    }
+
+    /// Store an orphan waiting on `missing_parents`
+    ///
+    /// Rejected if the peer is over its quota; otherwise the
+    /// oldest orphan is evicted when the pool is full.
+    pub fn add(
+        &mut self,
+        tx: Transaction,
+        peer: Option<SocketAddr>,
+        missing_parents: HashSet<Vec<u8>>,
+        now: i64,
+    ) -> bool {
+        let txid = tx.txid();
+        if self.orphans.contains_key(&txid) || missing_parents.is_empty() {
+            return false;
+        }
+
+        if let Some(p) = peer {
+            if self.per_peer.get(&p).copied().unwrap_or(0) >= MAX_ORPHANS_PER_PEER {
+                return false;
+            }
+        }
+
+        while self.orphans.len() >= MAX_ORPHAN_TXS {
+            let oldest = self
+                .orphans
+                .iter()
+                .min_by_key(|(_, o)| o.received)
+                .map(|(id, _)| id.clone());
+
+            match oldest {
+                Some(id) => {
+                    self.remove(&id);
+                }
+                None => break,
+            }
+        }
+
+        for parent in &missing_parents {
+            self.by_parent
+                .entry(parent.clone())
+                .or_default()
+                .insert(txid.clone());
+        }
+
+        if let Some(p) = peer {
+            *self.per_peer.entry(p).or_insert(0) += 1;
+        }
+
+        self.orphans.insert(txid, Orphan { tx, peer, received: now, missing_parents });
+        true
+    }
+
+    pub fn remove(&mut self, txid: &[u8]) -> Option<Orphan> {
+        let orphan = self.orphans.remove(txid)?;
+
+        for parent in &orphan.missing_parents {
+            if let Some(children) = self.by_parent.get_mut(parent) {
+                children.remove(txid);
+                if children.is_empty() {
+                    self.by_parent.remove(parent);
+                }
+            }
+        }
+
+        if let Some(p) = orphan.peer {
+            if let Some(n) = self.per_peer.get_mut(&p) {
+                *n -= 1;
+                if *n == 0 {
+                    self.per_peer.remove(&p);
+                }
+            }
+        }
+
+        Some(orphan)
+    }
+
+    /// Remove and return every orphan spending `parent`'s outputs
+    pub fn take_children(&mut self, parent: &[u8]) -> Vec<Orphan> {
+        let children: Vec<Vec<u8>> = match self.by_parent.get(parent) {
+            Some(c) => c.iter().cloned().collect(),
+            None => return Vec::new(),
+        };
+
+        children.iter().filter_map(|id| self.remove(id)).collect()
+    }
+
+    /// Drop orphans older than ORPHAN_EXPIRY_SECS
+    pub fn expire(&mut self, now: i64) -> usize {
+        let cutoff = now - ORPHAN_EXPIRY_SECS;
+        let expired: Vec<Vec<u8>> = self
+            .orphans
+            .iter()
+            .filter(|(_, o)| o.received < cutoff)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in &expired {
+            self.remove(id);
+        }
+        expired.len()
+    }
+
+    /// Drop everything relayed by a disconnected peer
+    pub fn remove_for_peer(&mut self, peer: SocketAddr) {
+        let ids: Vec<Vec<u8>> = self
+            .orphans
+            .iter()
+            .filter(|(_, o)| o.peer == Some(peer))
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in ids {
+            self.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::transaction::TxOutput;
+
+    /// Distinct stand-in orphan
+    fn tx(i: u64) -> Transaction {
+        Transaction::new(Vec::new(), vec![TxOutput::pay_to_pubkey_hash(i, vec![1u8; 32])])
+    }
+
+    fn parent(i: u8) -> HashSet<Vec<u8>> {
+        [vec![i; 32]].into_iter().collect()
+    }
+
+    fn peer(port: u16) -> Option<SocketAddr> {
+        Some(SocketAddr::from(([10, 0, 0, 1], port)))
+    }
+
+    #[test]
+    fn total_limit_evicts_oldest() {
+        let mut pool = OrphanPool::new();
+
+        for i in 0..=MAX_ORPHAN_TXS as u64 {
+            assert!(pool.add(tx(i), None, parent(1), i as i64));
+        }
+
+        assert_eq!(pool.len(), MAX_ORPHAN_TXS);
+        assert!(!pool.contains(&tx(0).txid()));
+        assert!(pool.contains(&tx(1).txid()));
+        assert!(pool.contains(&tx(MAX_ORPHAN_TXS as u64).txid()));
+    }
+
+    #[test]
+    fn per_peer_limit() {
+        let mut pool = OrphanPool::new();
+
+        for i in 0..MAX_ORPHANS_PER_PEER as u64 {
+            assert!(pool.add(tx(i), peer(1), parent(1), 0));
+        }
+        assert!(!pool.add(tx(100), peer(1), parent(1), 0));
+
+        // Other peers and local submissions are unaffected
+        assert!(pool.add(tx(100), peer(2), parent(1), 0));
+        assert!(pool.add(tx(101), None, parent(1), 0));
+
+        // Quota frees up as the peer's orphans leave
+        pool.remove(&tx(0).txid());
+        assert!(pool.add(tx(102), peer(1), parent(1), 0));
+
+        pool.remove_for_peer(peer(1).unwrap());
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn expires_old_orphans() {
+        let mut pool = OrphanPool::new();
+        assert!(pool.add(tx(0), peer(1), parent(1), 0));
+        assert!(pool.add(tx(1), peer(1), parent(1), 100));
+
+        assert_eq!(pool.expire(ORPHAN_EXPIRY_SECS), 0);
+        assert_eq!(pool.expire(ORPHAN_EXPIRY_SECS + 1), 1);
+        assert!(!pool.contains(&tx(0).txid()));
+        assert!(pool.contains(&tx(1).txid()));
+    }
+
+    #[test]
+    fn children_taken_by_parent() {
+        let mut pool = OrphanPool::new();
+        let both: HashSet<Vec<u8>> = parent(1).union(&parent(2)).cloned().collect();
+
+        assert!(pool.add(tx(0), None, parent(1), 0));
+        assert!(pool.add(tx(1), None, both, 0));
+        assert!(pool.add(tx(2), None, parent(2), 0));
+
+        let mut taken: Vec<Vec<u8>> = pool
+            .take_children(&[1u8; 32])
+            .iter()
+            .map(|o| o.tx.txid())
+            .collect();
+        taken.sort();
+        let mut expected = vec![tx(0).txid(), tx(1).txid()];
+        expected.sort();
+
+        assert_eq!(taken, expected);
+        assert!(pool.take_children(&[1u8; 32]).is_empty());
+        assert_eq!(pool.take_children(&[2u8; 32]).len(), 1);
+        assert!(pool.is_empty());
+    }
+}
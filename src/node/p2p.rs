@@ -1,18 +1,63 @@
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use time::OffsetDateTime;
 
 // Required imports from the project structure
 use crate::core::block::Block;
-use crate::core::chain::Blockchain;
-use crate::validation::validate_transaction;
-use crate::node::message::{NetworkMessage, PROTOCOL_VERSION};
-use crate::node::transport::Transport;
+use crate::core::chain::{BranchError, Blockchain, median_time_past};
+use crate::core::transaction::Transaction;
+use crate::node::mempool::{Mempool, TxAcceptance};
+use crate::node::inventory::KnownInventory;
+use crate::node::message::{
+    NetworkMessage, InvItem, InvKind, PROTOCOL_VERSION, MIN_PROTOCOL_VERSION, MAX_INV_ITEMS, MAX_HEADERS,
+    MAX_LOCATOR_HASHES, MAX_BLOCKS_PER_GETDATA, MAX_GETDATA_REPLIES, LOCAL_SERVICES,
+    NODE_NETWORK, USER_AGENT,
+};
+use crate::consensus::params::GENESIS_HASH;
+use crate::node::addrman::{peers_file, AddrMan, MAX_ADDR_PER_MESSAGE};
+use crate::node::peer::{check_chain_identity, PeerState};
+use crate::node::transport::frame::NETWORK_MAGIC;
+use crate::node::sync::BlockSync;
+use crate::node::transport::{PeerEvent, Transport};
+
+/// Outbound connections kept open from the address manager
+pub const MAX_OUTBOUND_PEERS: usize = 8;
+
+/// Connectionless sources remembered after their Hello
+pub const MAX_CONNECTIONLESS_SOURCES: usize = 1_024;
+
+/// How long a connectionless source's Hello is honoured
+pub const CONNECTIONLESS_HELLO_TTL: Duration = Duration::from_secs(60 * 60);
+
+fn now() -> i64 {
+    OffsetDateTime::now_utc().unix_timestamp()
+}
 
 /// The P2P Network Layer
 /// Handles peer communication and message broadcasting
 pub struct P2PNetwork {
     transport: Arc<dyn Transport>,
     chain: Arc<Mutex<Blockchain>>,
+    mempool: Arc<Mutex<Mempool>>,
+    /// Handshake state of connected peers
+    peers: Mutex<HashMap<SocketAddr, PeerState>>,
+    /// Known addresses, drives outbound connections
+    addrman: Mutex<AddrMan>,
+    /// Per-peer inventory already sent or received
+    known: Mutex<HashMap<SocketAddr, KnownInventory>>,
+    /// Headers-first download state (lock after chain)
+    sync: Mutex<BlockSync>,
+    /// Connectionless sources that sent a valid Hello:
+    /// addr → (Hello received at, negotiated version)
+    sources: Mutex<HashMap<SocketAddr, (Instant, u32)>>,
+    /// Port we accept connections on, sent in Listen
+    listen_port: Option<u16>,
+    /// Wallet CLI: handshake and push transactions only
+    push_only: bool,
 }
 
 impl P2PNetwork {
@@ -21,89 +66,844 @@ impl P2PNetwork {
     pub fn new(
         transport: Arc<dyn Transport>,
         chain: Arc<Mutex<Blockchain>>,
+        mempool: Arc<Mutex<Mempool>>,
+        addrman: AddrMan,
     ) -> Self {
         // System logs to show network status
         println!("> [SYSTEM] Initializing P2P Network Layer...");
         println!("> [INFO] Protocol Version: {}", PROTOCOL_VERSION);
         println!("> [STATUS] Node is active and listening...");
 
-        Self { transport, chain }
+        let sync = BlockSync::new(&chain.lock().unwrap().blocks);
+
+        Self {
+            transport,
+            chain,
+            mempool,
+            peers: Mutex::new(HashMap::new()),
+            addrman: Mutex::new(addrman),
+            known: Mutex::new(HashMap::new()),
+            sync: Mutex::new(sync),
+            sources: Mutex::new(HashMap::new()),
+            listen_port: None,
+            push_only: false,
+        }
+    }
+
+    /// Node accepting connections on `port`, which it
+    /// advertises to peers with Listen
+    pub fn listening(
+        transport: Arc<dyn Transport>,
+        chain: Arc<Mutex<Blockchain>>,
+        mempool: Arc<Mutex<Mempool>>,
+        addrman: AddrMan,
+        port: u16,
+    ) -> Self {
+        Self {
+            listen_port: Some(port),
+            ..Self::new(transport, chain, mempool, addrman)
+        }
+    }
+
+    /// Short-lived session for the wallet CLI
+    ///
+    /// Completes handshakes and delivers transactions with
+    /// `push_transaction`; everything else peers send is
+    /// ignored, so the CLI never syncs, relays or writes the
+    /// chain or address files a running node owns.
+    pub fn push_only(
+        transport: Arc<dyn Transport>,
+        chain: Arc<Mutex<Blockchain>>,
+        mempool: Arc<Mutex<Mempool>>,
+    ) -> Self {
+        Self {
+            push_only: true,
+            ..Self::new(transport, chain, mempool, AddrMan::new())
+        }
     }
 
     /// Handle incoming messages from peers
     pub fn on_receive(&self, addr: SocketAddr, data: Vec<u8>) {
-        // Deserialize message safely
-        let msg: NetworkMessage = match bincode::deserialize(&data) {
+        // Decode frame + message safely
+        let msg = match NetworkMessage::from_frame(&data) {
             Ok(m) => m,
-            Err(_) => {
-                println!("> [WARN] Invalid packet received from {}", addr);
+            Err(e) => {
+                println!("> [WARN] Invalid packet received from {}: {}", addr, e);
                 return;
             }
         };
 
+        // Connected peers must finish the handshake first and
+        // then only use messages of the negotiated version
+        let state = self
+            .peers
+            .lock()
+            .unwrap()
+            .get(&addr)
+            .map(|p| (p.is_ready(), p.supports(&msg)));
+
+        match state {
+            Some((false, _)) if !matches!(msg, NetworkMessage::Hello { .. } | NetworkMessage::VerAck) => {
+                self.drop_peer(addr, "message before handshake");
+                return;
+            }
+            Some((_, false)) => {
+                self.drop_peer(addr, "message above negotiated version");
+                return;
+            }
+            Some(_) => {}
+            None => {
+                if !self.accept_connectionless(addr, &msg) {
+                    return;
+                }
+            }
+        }
+
+        if self.push_only
+            && !matches!(
+                msg,
+                NetworkMessage::Hello { .. }
+                    | NetworkMessage::VerAck
+                    | NetworkMessage::Ping
+                    | NetworkMessage::Pong
+            )
+        {
+            return;
+        }
+
         // Process message with system logging
         match msg {
-            NetworkMessage::Hello { version, height, .. } => {
+            NetworkMessage::Hello { version, services, height, agent, genesis_hash, magic } => {
                 println!("> [NET] Handshake request from {} (Height: {})", addr, height);
 
-                if version != PROTOCOL_VERSION {
-                    println!("> [DENY] Protocol mismatch with {}", addr);
+                if let Err(e) = check_chain_identity(&genesis_hash, magic) {
+                    let outbound = self.peers.lock().unwrap().get(&addr).is_some_and(|p| p.outbound);
+
+                    // Another chain: never dial that address again. An
+                    // inbound peer is only disconnected; its host may
+                    // well run a node on our chain too
+                    if outbound {
+                        self.addrman.lock().unwrap().reject(addr, now());
+                    }
+                    self.drop_peer(addr, e);
+                    return;
+                }
+
+                let result = match self.peers.lock().unwrap().get_mut(&addr) {
+                    Some(peer) => peer.on_hello(version, services, height, agent),
+                    // Connectionless source: nothing to negotiate
+                    None => return,
+                };
+
+                if let Err(e) = result {
+                    self.drop_peer(addr, e);
+                    return;
+                }
+
+                self.send(addr, &NetworkMessage::VerAck);
+                self.on_handshake_step(addr);
+            }
+
+            NetworkMessage::VerAck => {
+                let result = match self.peers.lock().unwrap().get_mut(&addr) {
+                    Some(peer) => peer.on_verack(),
+                    None => return,
+                };
+
+                if let Err(e) = result {
+                    self.drop_peer(addr, e);
+                    return;
+                }
+
+                self.on_handshake_step(addr);
+            }
+
+            NetworkMessage::GetAddr => {
+                let addrs = self.addrman.lock().unwrap().get_addr(now());
+                self.send(addr, &NetworkMessage::Addr(
+                    addrs.iter().map(|a| a.to_string()).collect(),
+                ));
+            }
+
+            NetworkMessage::Addr(list) => {
+                if list.len() > MAX_ADDR_PER_MESSAGE {
+                    self.drop_peer(addr, "oversized addr message");
                     return;
                 }
 
-                let local_height = self.chain.lock().unwrap().height();
-                if height > local_height {
-                    println!("> [SYNC] Peer is ahead. Requesting blocks...");
-                    self.send(addr, &NetworkMessage::SyncRequest { from_height: local_height });
+                // Only from handshaked connections: a connectionless
+                // source's IP is spoofable and picks the new buckets
+                let allowed = match self.peers.lock().unwrap().get_mut(&addr) {
+                    Some(peer) if peer.is_ready() => {
+                        peer.take_addr_tokens(list.len(), Instant::now())
+                    }
+                    _ => return,
+                };
+
+                let addrs: Vec<SocketAddr> = list
+                    .iter()
+                    .take(allowed)
+                    .filter_map(|a| a.parse().ok())
+                    .collect();
+                let added = self.addrman.lock().unwrap().add(&addrs, addr.ip(), now());
+
+                if added > 0 {
+                    println!("> [NET] Learned {} address(es) from {}", added, addr);
                 }
             }
 
             NetworkMessage::SyncRequest { from_height } => {
-                println!("> [QUERY] Serving blocks from height {}", from_height);
-                let c = self.chain.lock().unwrap();
-                for b in c.blocks.iter().skip(from_height as usize) {
-                    self.send(addr, &NetworkMessage::Block(b.clone()));
+                println!("> [QUERY] Announcing blocks from height {}", from_height);
+
+                // Peer fetches only the blocks it lacks via GetData
+                let items: Vec<InvItem> = {
+                    let c = self.chain.lock().unwrap();
+                    c.blocks
+                        .iter()
+                        .skip(from_height as usize)
+                        .take(MAX_INV_ITEMS)
+                        .map(|b| InvItem::block(&b.hash))
+                        .collect()
+                };
+
+                if !items.is_empty() {
+                    self.send(addr, &NetworkMessage::Inv(items));
                 }
             }
 
+            NetworkMessage::GetHeaders { locator, stop } => {
+                let headers = self.chain.lock().unwrap().headers_after(
+                    &locator[..locator.len().min(MAX_LOCATOR_HASHES)],
+                    stop.as_deref(),
+                    MAX_HEADERS,
+                );
+
+                self.send(addr, &NetworkMessage::Headers(headers));
+            }
+
+            NetworkMessage::Headers(headers) => {
+                if headers.len() > MAX_HEADERS {
+                    println!("> [DENY] Oversized headers message from {}", addr);
+                    return;
+                }
+
+                let full = headers.len() == MAX_HEADERS;
+
+                let outcome = {
+                    let c = self.chain.lock().unwrap();
+                    self.sync.lock().unwrap().on_headers(&c.blocks, headers)
+                };
+
+                match outcome {
+                    Ok(0) => {}
+                    Ok(n) => {
+                        println!("> [SYNC] {} new header(s) from {}", n, addr);
+
+                        // A full batch means the peer has more
+                        if full {
+                            self.request_headers(addr);
+                        }
+                        self.request_blocks();
+                    }
+                    Err(e) => println!("> [DENY] Headers from {}: {}", addr, e),
+                }
+            }
+
+            NetworkMessage::Inv(items) => {
+                let mut wanted: Vec<InvItem> = items
+                    .into_iter()
+                    .take(MAX_INV_ITEMS)
+                    .filter(|item| {
+                        self.mark_known(addr, item.clone());
+                        !self.have(item)
+                    })
+                    .collect();
+
+                // Unknown blocks are fetched headers-first,
+                // so we learn where they connect
+                let blocks = wanted.len();
+                wanted.retain(|item| item.kind != InvKind::Block);
+                if wanted.len() != blocks {
+                    self.request_headers(addr);
+                }
+
+                // The peer serves at most this many per request
+                wanted.truncate(MAX_GETDATA_REPLIES);
+
+                if !wanted.is_empty() {
+                    println!("> [INV] Requesting {} item(s) from {}", wanted.len(), addr);
+                    self.send(addr, &NetworkMessage::GetData(wanted));
+                }
+            }
+
+            NetworkMessage::GetData(items) => {
+                let mut not_found = Vec::new();
+                let mut served = 0;
+                let mut blocks_served = 0;
+
+                // Stay well inside the peer's send queue; anything
+                // past the limits is ignored and asked for again
+                for item in items.into_iter().take(MAX_INV_ITEMS) {
+                    if served >= MAX_GETDATA_REPLIES {
+                        break;
+                    }
+                    let is_block = item.kind == InvKind::Block;
+                    if is_block && blocks_served >= MAX_BLOCKS_PER_GETDATA {
+                        continue;
+                    }
+
+                    match self.lookup(&item) {
+                        Some(msg) => {
+                            self.send(addr, &msg);
+                            self.mark_known(addr, item);
+                            served += 1;
+                            if is_block {
+                                blocks_served += 1;
+                            }
+                        }
+                        None => not_found.push(item),
+                    }
+                }
+
+                if !not_found.is_empty() {
+                    self.send(addr, &NetworkMessage::NotFound(not_found));
+                }
+            }
+
+            NetworkMessage::NotFound(items) => {
+                println!("> [INV] {} item(s) not found at {}", items.len(), addr);
+            }
+
             NetworkMessage::Block(block) => {
                 println!("> [BLOCK] New block received. Validating...");
-                self.chain.lock().unwrap().validate_and_add_block(block);
-                println!("> [SUCCESS] Block added to chain.");
+                self.mark_known(addr, InvItem::block(&block.hash));
+
+                let hash = block.hash.clone();
+
+                if self.sync.lock().unwrap().on_block(block.clone(), addr) {
+                    self.connect_downloaded(addr);
+                    self.request_blocks();
+                } else {
+                    let tip = {
+                        let mut c = self.chain.lock().unwrap();
+                        if !c.validate_and_add_block(block) {
+                            return;
+                        }
+                        c.blocks.last().map(|b| b.hash.clone())
+                    };
+                    println!("> [SUCCESS] Block added to chain.");
+
+                    // Relay only if it became our tip (not a losing side branch)
+                    if tip.as_deref() == Some(hash.as_slice()) {
+                        self.announce(InvItem::block(&hash), Some(addr));
+                    }
+                }
             }
 
             NetworkMessage::Transaction(tx) => {
                 println!("> [TX] Processing incoming transaction...");
-                let c = self.chain.lock().unwrap();
-                let _ = validate_transaction(&tx, &c.utxos, c.height());
+                self.mark_known(addr, InvItem::transaction(&tx.txid()));
+
+                // Lock order: mempool → chain
+                let outcome = {
+                    let mut m = self.mempool.lock().unwrap();
+                    let c = self.chain.lock().unwrap();
+                    let mtp = median_time_past(&c.blocks);
+                    m.accept_from_peer(tx.clone(), Some(addr), &c.utxos, c.height(), mtp)
+                };
+
+                match outcome {
+                    TxAcceptance::Accepted { resolved } => {
+                        println!("> [TX] Accepted {}. Relaying...", hex::encode(tx.txid()));
+                        self.relay_transaction(&tx, Some(addr));
+
+                        // Orphans unblocked by this parent
+                        for orphan in &resolved {
+                            self.relay_transaction(orphan, None);
+                        }
+                    }
+                    TxAcceptance::Orphaned => {
+                        println!("> [TX] Parents unknown. Held as orphan.");
+                    }
+                    TxAcceptance::Rejected => {}
+                }
             }
 
             NetworkMessage::Ping => {
                 self.send(addr, &NetworkMessage::Pong);
             }
 
-            _ => {}
+            NetworkMessage::Pong => {
+                if let Some(peer) = self.peers.lock().unwrap().get_mut(&addr) {
+                    peer.awaiting_pong = false;
+                }
+            }
+
+            NetworkMessage::Listen { port } => {
+                let inbound = match self.peers.lock().unwrap().get_mut(&addr) {
+                    Some(peer) => {
+                        peer.listen_port = Some(port);
+                        !peer.outbound
+                    }
+                    None => return,
+                };
+
+                // An inbound peer's own address: reachable as far as
+                // it claims, so it starts in the new table like gossip
+                if inbound {
+                    let listen = SocketAddr::new(addr.ip(), port);
+                    if self.addrman.lock().unwrap().add(&[listen], addr.ip(), now()) > 0 {
+                        println!("> [NET] Learned {} from its Listen", listen);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Gate for sources without a connection (UDP, satellite, BLE)
+    ///
+    /// They cannot answer a handshake, but must still send a
+    /// Hello naming our chain before anything else is taken
+    /// from them. A Hello counts for CONNECTIONLESS_HELLO_TTL.
+    fn accept_connectionless(&self, addr: SocketAddr, msg: &NetworkMessage) -> bool {
+        let now = Instant::now();
+        let mut sources = self.sources.lock().unwrap();
+
+        if let NetworkMessage::Hello { version, genesis_hash, magic, .. } = msg {
+            sources.remove(&addr);
+
+            if *version < MIN_PROTOCOL_VERSION {
+                println!("> [DENY] Hello from {}: protocol version too old", addr);
+                return false;
+            }
+            if let Err(e) = check_chain_identity(genesis_hash, *magic) {
+                println!("> [DENY] Hello from {}: {}", addr, e);
+                return false;
+            }
+
+            // Full: forget the source heard from longest ago
+            if sources.len() >= MAX_CONNECTIONLESS_SOURCES {
+                let oldest = sources.iter().min_by_key(|(_, (at, _))| *at).map(|(a, _)| *a);
+                if let Some(oldest) = oldest {
+                    sources.remove(&oldest);
+                }
+            }
+
+            sources.insert(addr, (now, (*version).min(PROTOCOL_VERSION)));
+            println!("> [NET] Accepted connectionless source {}", addr);
+            return false;
+        }
+
+        match sources.get(&addr) {
+            Some((at, _)) if now.duration_since(*at) > CONNECTIONLESS_HELLO_TTL => {
+                sources.remove(&addr);
+                println!("> [WARN] Dropped message from {}: Hello expired", addr);
+                false
+            }
+            Some((_, version)) if msg.min_version() <= *version => true,
+            _ => {
+                println!("> [WARN] Dropped message from {}: no Hello", addr);
+                false
+            }
+        }
+    }
+
+    /// Connection lifecycle from the transport
+    pub fn on_peer_event(&self, event: PeerEvent) {
+        match event {
+            PeerEvent::Connected { addr, outbound } => {
+                let dir = if outbound { "outbound" } else { "inbound" };
+                println!("> [NET] Connected to {} ({})", addr, dir);
+
+                self.peers
+                    .lock()
+                    .unwrap()
+                    .insert(addr, PeerState::new(outbound, Instant::now()));
+
+                let height = self.chain.lock().unwrap().height();
+
+                // A push-only session serves nothing
+                let services = if self.push_only { 0 } else { LOCAL_SERVICES };
+
+                self.send(addr, &NetworkMessage::Hello {
+                    version: PROTOCOL_VERSION,
+                    services,
+                    height,
+                    agent: USER_AGENT.to_string(),
+                    genesis_hash: GENESIS_HASH.to_vec(),
+                    magic: NETWORK_MAGIC,
+                });
+            }
+
+            PeerEvent::Disconnected(addr) => {
+                println!("> [NET] Disconnected from {}", addr);
+
+                self.peers.lock().unwrap().remove(&addr);
+                self.known.lock().unwrap().remove(&addr);
+                self.sync.lock().unwrap().remove_peer(&addr);
+                self.mempool.lock().unwrap().remove_orphans_for_peer(addr);
+
+                // Its block requests go to the remaining peers
+                self.request_blocks();
+            }
+        }
+    }
+
+    /// Called after Hello / VerAck; acts once both are in
+    fn on_handshake_step(&self, addr: SocketAddr) {
+        let (version, start_height, outbound) = match self.peers.lock().unwrap().get(&addr) {
+            Some(p) if p.is_ready() => (p.version.unwrap_or(0), p.start_height, p.outbound),
+            _ => return,
+        };
+
+        println!("> [NET] Handshake complete with {} (v{})", addr, version);
+
+        if self.push_only {
+            return;
+        }
+
+        // Dropped by `send` for peers below v9
+        if let Some(port) = self.listen_port {
+            self.send(addr, &NetworkMessage::Listen { port });
+        }
+
+        // Only outbound addresses are known to accept connections
+        if outbound {
+            self.addrman.lock().unwrap().mark_good(addr, now());
+            self.send(addr, &NetworkMessage::GetAddr);
+        }
+
+        let local_height = self.chain.lock().unwrap().height();
+        if start_height > local_height {
+            println!("> [SYNC] Peer is ahead. Requesting headers...");
+            self.request_headers(addr);
         }
     }
 
+    /// Disconnect a peer that broke protocol
+    fn drop_peer(&self, addr: SocketAddr, reason: &str) {
+        println!("> [DENY] Disconnecting {}: {}", addr, reason);
+        self.transport.disconnect(&addr);
+    }
+
+    /// Peers that completed the handshake
+    fn ready_peers(&self) -> Vec<SocketAddr> {
+        self.peers
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, p)| p.is_ready())
+            .map(|(addr, _)| *addr)
+            .collect()
+    }
+
     /// Helper function to send messages to a single peer
     fn send(&self, addr: SocketAddr, msg: &NetworkMessage) {
-        if let Ok(data) = bincode::serialize(msg) {
+        // Nothing the peer's negotiated version lacks
+        let supported = self.peers.lock().unwrap().get(&addr).is_none_or(|p| p.supports(msg));
+        if !supported {
+            return;
+        }
+
+        if let Some(data) = msg.to_frame() {
             self.transport.send(&addr, &data);
         }
     }
 
-    /// ✅ FIX: Broadcast a newly mined block to all peers
+    /// Ask a peer for headers past our best header chain
+    fn request_headers(&self, addr: SocketAddr) {
+        let locator = self.sync.lock().unwrap().locator();
+        self.send(addr, &NetworkMessage::GetHeaders { locator, stop: None });
+    }
+
+    /// Send GetData for missing bodies to peers with free slots
+    fn request_blocks(&self) {
+        let peers: Vec<SocketAddr> = self
+            .peers
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, p)| p.is_ready() && p.has_service(NODE_NETWORK))
+            .map(|(addr, _)| *addr)
+            .collect();
+
+        let batches = {
+            let c = self.chain.lock().unwrap();
+            self.sync
+                .lock()
+                .unwrap()
+                .request_blocks(&c.blocks, &peers, Instant::now())
+        };
+
+        for (peer, items) in batches {
+            self.send(peer, &NetworkMessage::GetData(items));
+        }
+    }
+
+    /// Connect downloaded bodies to the active chain and
+    /// announce the new tip to everyone but `from`
+    ///
+    /// A peer whose block fails validation is disconnected.
+    fn connect_downloaded(&self, from: SocketAddr) {
+        let mut new_tip = None;
+        let mut offender = None;
+
+        {
+            let mut c = self.chain.lock().unwrap();
+            let mut sync = self.sync.lock().unwrap();
+
+            while let Some(branch) = sync.next_branch(&c.blocks) {
+                let tip = branch.last().map(|(b, _)| b.header.height).unwrap_or(0);
+                let (blocks, sources): (Vec<Block>, Vec<SocketAddr>) = branch.into_iter().unzip();
+
+                match c.connect_branch(blocks) {
+                    Ok(()) => {}
+                    Err(BranchError::Invalid(index)) => {
+                        println!("> [DENY] Downloaded block failed validation. Resetting sync.");
+                        offender = Some(sources[index]);
+                        sync.reset(&c.blocks);
+                        break;
+                    }
+                    Err(_) => {
+                        // Not the sender's fault: the active chain
+                        // moved on since the headers were adopted
+                        println!("> [SYNC] Downloaded blocks no longer lead the active chain.");
+                        sync.reset(&c.blocks);
+                        break;
+                    }
+                }
+
+                println!("> [SYNC] Connected blocks up to height {}", tip);
+                new_tip = c.blocks.last().map(|b| b.hash.clone());
+            }
+        }
+
+        if let Some(peer) = offender {
+            self.drop_peer(peer, "sent an invalid block");
+        }
+
+        if let Some(hash) = new_tip {
+            self.announce(InvItem::block(&hash), Some(from));
+        }
+    }
+
+    /// Periodic housekeeping: handshake timeouts,
+    /// stalled block downloads
+    pub fn maintain(&self) {
+        let now = Instant::now();
+
+        let timed_out: Vec<SocketAddr> = self
+            .peers
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, p)| p.timed_out(now))
+            .map(|(addr, _)| *addr)
+            .collect();
+
+        for peer in timed_out {
+            self.drop_peer(peer, "handshake timeout");
+        }
+
+        let stalled = self.sync.lock().unwrap().stalled_peers(now);
+
+        for peer in stalled {
+            println!("> [SYNC] Peer {} stalled block download. Replacing...", peer);
+            self.transport.disconnect(&peer);
+        }
+
+        self.request_blocks();
+        self.open_outbound();
+    }
+
+    /// Dial one address-manager pick if below MAX_OUTBOUND_PEERS
+    fn open_outbound(&self) {
+        let (connected, outbound) = {
+            let peers = self.peers.lock().unwrap();
+            let connected: HashSet<SocketAddr> = peers.keys().copied().collect();
+            let outbound = peers.values().filter(|p| p.outbound).count();
+            (connected, outbound)
+        };
+
+        if outbound >= MAX_OUTBOUND_PEERS {
+            return;
+        }
+
+        let target = {
+            let mut addrman = self.addrman.lock().unwrap();
+            let target = addrman.select(&connected, now());
+            if let Some(t) = target {
+                addrman.mark_attempt(t, now());
+            }
+            target
+        };
+
+        // Connecting blocks; keep housekeeping responsive
+        if let Some(target) = target {
+            let transport = Arc::clone(&self.transport);
+            thread::spawn(move || transport.connect(&target));
+        }
+    }
+
+    /// Persist the address manager
+    pub fn save_addresses(&self) -> Result<(), &'static str> {
+        self.addrman.lock().unwrap().save(&peers_file())
+    }
+
+    /// True once every known best-chain block is connected
+    pub fn is_synced(&self) -> bool {
+        let c = self.chain.lock().unwrap();
+        self.sync.lock().unwrap().is_synced(&c.blocks)
+    }
+
+    /// Block until every connected peer finished the handshake
+    /// (or `timeout`); returns the number of ready peers
+    pub fn wait_for_handshakes(&self, timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+
+        while Instant::now() < deadline {
+            let pending = self.peers.lock().unwrap().values().any(|p| !p.is_ready());
+            if !pending {
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        self.peer_count()
+    }
+
+    /// Number of peers that completed the handshake
+    pub fn peer_count(&self) -> usize {
+        self.ready_peers().len()
+    }
+
+    /// Send a transaction in full to every ready peer and wait
+    /// until each has processed it (or `timeout`)
+    ///
+    /// Each copy is followed by a Ping. A connection's frames
+    /// are handled in order, so the Pong means the peer has
+    /// taken the transaction. Returns the number that did.
+    pub fn push_transaction(&self, tx: &Transaction, timeout: Duration) -> usize {
+        let item = InvItem::transaction(&tx.txid());
+        let targets = self.ready_peers();
+
+        for peer in &targets {
+            if let Some(p) = self.peers.lock().unwrap().get_mut(peer) {
+                p.awaiting_pong = true;
+            }
+            self.mark_known(*peer, item.clone());
+            self.send(*peer, &NetworkMessage::Transaction(tx.clone()));
+            self.send(*peer, &NetworkMessage::Ping);
+        }
+
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let (delivered, waiting) = {
+                let peers = self.peers.lock().unwrap();
+                let states: Vec<bool> = targets
+                    .iter()
+                    .filter_map(|t| peers.get(t).map(|p| p.awaiting_pong))
+                    .collect();
+                let waiting = states.iter().filter(|w| **w).count();
+                (states.len() - waiting, waiting)
+            };
+
+            if waiting == 0 || Instant::now() >= deadline {
+                return delivered;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    /// Announce a locally submitted transaction to all peers
+    pub fn broadcast_transaction(&self, tx: &Transaction) {
+        self.relay_transaction(tx, None);
+    }
+
+    /// Announce a mempool-accepted transaction to every peer
+    /// except the one it came from
+    fn relay_transaction(&self, tx: &Transaction, except: Option<SocketAddr>) {
+        self.announce(InvItem::transaction(&tx.txid()), except);
+    }
+
+    /// Announce a newly mined block to all peers
+    ///
+    /// Peers request the body with GetData only if they lack it.
     pub fn broadcast_block(&self, block: &Block) {
         println!(
-            "> [NET] Broadcasting block at height {}",
+            "> [NET] Announcing block at height {}",
             block.header.height
         );
 
-        let msg = NetworkMessage::Block(block.clone());
+        self.announce(InvItem::block(&block.hash), None);
+    }
+
+    /// Send `Inv` to every peer not already known to have `item`
+    fn announce(&self, item: InvItem, except: Option<SocketAddr>) {
+        let msg = NetworkMessage::Inv(vec![item.clone()]);
+
+        let data = match msg.to_frame() {
+            Some(d) => d,
+            None => return,
+        };
+
+        // Sent with `known` unlocked: a send may disconnect the peer
+        let targets: Vec<SocketAddr> = {
+            let mut known = self.known.lock().unwrap();
+
+            self.ready_peers()
+                .into_iter()
+                .filter(|peer| Some(*peer) != except)
+                .filter(|peer| known.entry(*peer).or_default().insert(item.clone()))
+                .collect()
+        };
+
+        for peer in targets {
+            self.transport.send(&peer, &data);
+        }
+    }
+
+    fn mark_known(&self, peer: SocketAddr, item: InvItem) {
+        self.known
+            .lock()
+            .unwrap()
+            .entry(peer)
+            .or_default()
+            .insert(item);
+    }
+
+    /// True if the object is already in our chain, mempool or orphan pool
+    fn have(&self, item: &InvItem) -> bool {
+        match item.kind {
+            InvKind::Block => self
+                .chain
+                .lock()
+                .unwrap()
+                .blocks
+                .iter()
+                .any(|b| b.hash == item.hash),
+            InvKind::Transaction => self.mempool.lock().unwrap().knows(&item.hash),
+        }
+    }
 
-        if let Ok(data) = bincode::serialize(&msg) {
-            self.transport.broadcast(&data);
+    /// Full object for a GetData request
+    fn lookup(&self, item: &InvItem) -> Option<NetworkMessage> {
+        match item.kind {
+            InvKind::Block => self
+                .chain
+                .lock()
+                .unwrap()
+                .blocks
+                .iter()
+                .find(|b| b.hash == item.hash)
+                .map(|b| NetworkMessage::Block(b.clone())),
+            InvKind::Transaction => self
+                .mempool
+                .lock()
+                .unwrap()
+                .get(&item.hash)
+                .map(|e| NetworkMessage::Transaction(e.tx.clone())),
         }
     }
 }
\ No newline at end of file
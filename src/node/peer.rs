@@ -0,0 +1,186 @@
+use std::time::{Duration, Instant};
+
+use crate::consensus::params::GENESIS_HASH;
+use crate::node::addrman::MAX_ADDR_PER_MESSAGE;
+use crate::node::message::{NetworkMessage, LOCAL_SERVICES, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+use crate::node::transport::frame::NETWORK_MAGIC;
+
+/// Time a new connection has to complete Hello / VerAck
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Gossiped addresses accepted per peer per second, sustained
+pub const ADDR_RATE_PER_SEC: f64 = 0.1;
+
+/// Addresses a peer may send at once (one full GetAddr reply)
+pub const ADDR_BURST: f64 = MAX_ADDR_PER_MESSAGE as f64;
+
+/// A peer's Hello must name our chain
+///
+/// A node on another fork or network would reject all our
+/// blocks and we all of its; there is nothing to exchange.
+pub fn check_chain_identity(genesis_hash: &[u8], magic: [u8; 4]) -> Result<(), &'static str> {
+    if magic != NETWORK_MAGIC {
+        return Err("different network magic");
+    }
+
+    if genesis_hash != GENESIS_HASH {
+        return Err("different genesis block");
+    }
+
+    Ok(())
+}
+
+/// Per-connection handshake state
+///
+/// Both sides send `Hello` on connect and answer the other's
+/// `Hello` with `VerAck`. Until both have happened only those
+/// two messages are accepted.
+pub struct PeerState {
+    pub outbound: bool,
+    pub connected_at: Instant,
+    /// min(ours, theirs), set by their Hello
+    pub version: Option<u32>,
+    /// Service flags both sides offer
+    pub services: u64,
+    /// Their chain height at handshake
+    pub start_height: u64,
+    pub agent: String,
+    /// Port from their Listen (v9+)
+    pub listen_port: Option<u16>,
+    verack_received: bool,
+    /// Ping sent, Pong not yet received
+    pub awaiting_pong: bool,
+    /// Addr token bucket: (tokens, last refill)
+    addr_tokens: (f64, Instant),
+}
+
+impl PeerState {
+    pub fn new(outbound: bool, now: Instant) -> Self {
+        Self {
+            outbound,
+            connected_at: now,
+            version: None,
+            services: 0,
+            start_height: 0,
+            agent: String::new(),
+            listen_port: None,
+            verack_received: false,
+            awaiting_pong: false,
+            addr_tokens: (ADDR_BURST, now),
+        }
+    }
+
+    /// Handshake complete in both directions
+    pub fn is_ready(&self) -> bool {
+        self.version.is_some() && self.verack_received
+    }
+
+    pub fn has_service(&self, flag: u64) -> bool {
+        self.services & flag != 0
+    }
+
+    /// `msg` exists in the negotiated protocol version
+    ///
+    /// Before their Hello only the handshake messages do.
+    pub fn supports(&self, msg: &NetworkMessage) -> bool {
+        match self.version {
+            Some(version) => msg.min_version() <= version,
+            None => matches!(msg, NetworkMessage::Hello { .. } | NetworkMessage::VerAck),
+        }
+    }
+
+    /// Record the peer's Hello and negotiate version / services
+    pub fn on_hello(
+        &mut self,
+        version: u32,
+        services: u64,
+        height: u64,
+        agent: String,
+    ) -> Result<(), &'static str> {
+        if self.version.is_some() {
+            return Err("duplicate hello");
+        }
+
+        if version < MIN_PROTOCOL_VERSION {
+            return Err("protocol version too old");
+        }
+
+        self.version = Some(version.min(PROTOCOL_VERSION));
+        self.services = services & LOCAL_SERVICES;
+        self.start_height = height;
+        self.agent = agent;
+        Ok(())
+    }
+
+    /// Record the peer's VerAck (only valid after its Hello)
+    pub fn on_verack(&mut self) -> Result<(), &'static str> {
+        if self.version.is_none() {
+            return Err("verack before hello");
+        }
+
+        if self.verack_received {
+            return Err("duplicate verack");
+        }
+
+        self.verack_received = true;
+        Ok(())
+    }
+
+    /// How many of `count` gossiped addresses to accept now
+    ///
+    /// Refills at ADDR_RATE_PER_SEC up to ADDR_BURST; the
+    /// rest are ignored, so Addr floods cannot churn addrman.
+    pub fn take_addr_tokens(&mut self, count: usize, now: Instant) -> usize {
+        let (tokens, last) = self.addr_tokens;
+        let refilled = tokens + now.duration_since(last).as_secs_f64() * ADDR_RATE_PER_SEC;
+        let available = refilled.min(ADDR_BURST);
+
+        let taken = (count as f64).min(available.floor());
+        self.addr_tokens = (available - taken, now);
+        taken as usize
+    }
+
+    /// Still shaking hands after HANDSHAKE_TIMEOUT
+    pub fn timed_out(&self, now: Instant) -> bool {
+        !self.is_ready() && now.duration_since(self.connected_at) > HANDSHAKE_TIMEOUT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ready(version: u32) -> PeerState {
+        let mut peer = PeerState::new(true, Instant::now());
+        peer.on_hello(version, LOCAL_SERVICES, 0, String::new()).unwrap();
+        peer.on_verack().unwrap();
+        peer
+    }
+
+    #[test]
+    fn negotiates_the_lower_version() {
+        assert_eq!(ready(MIN_PROTOCOL_VERSION).version, Some(MIN_PROTOCOL_VERSION));
+        assert_eq!(ready(PROTOCOL_VERSION + 5).version, Some(PROTOCOL_VERSION));
+
+        let mut old = PeerState::new(false, Instant::now());
+        assert_eq!(
+            old.on_hello(MIN_PROTOCOL_VERSION - 1, 0, 0, String::new()),
+            Err("protocol version too old")
+        );
+    }
+
+    #[test]
+    fn messages_are_gated_on_negotiated_version() {
+        let listen = NetworkMessage::Listen { port: 8333 };
+
+        let fresh = PeerState::new(true, Instant::now());
+        assert!(!fresh.supports(&NetworkMessage::Ping));
+        assert!(fresh.supports(&NetworkMessage::VerAck));
+
+        let old = ready(MIN_PROTOCOL_VERSION);
+        assert!(old.supports(&NetworkMessage::GetHeaders { locator: Vec::new(), stop: None }));
+        assert!(!old.supports(&listen));
+
+        assert!(ready(PROTOCOL_VERSION).supports(&listen));
+    }
+}
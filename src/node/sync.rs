@@ -0,0 +1,490 @@
+// ─────────────────────────────────────────────
+// HEADERS-FIRST BLOCK DOWNLOAD (NETWORK ONLY)
+//
+// Peers first send headers, which are cheap to
+// check (link, difficulty, PoW). We follow the
+// best-work header chain and only then fetch the
+// bodies we lack, spread across peers.
+//
+// Bodies may arrive in any order; they are held
+// until they connect to the active chain. A peer
+// sitting on a request past BLOCK_STALL_TIMEOUT is
+// marked stalled and its blocks go to other peers.
+// ─────────────────────────────────────────────
+
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use num_bigint::BigUint;
+
+use crate::consensus::fork_choice::{block_work, cumulative_work};
+use crate::core::block::{Block, BlockHeader};
+use crate::core::chain::{block_locator, check_header};
+use crate::node::message::InvItem;
+
+/// Block requests outstanding per peer
+pub const MAX_BLOCKS_IN_FLIGHT_PER_PEER: usize = 16;
+
+/// Blocks past the active tip we download ahead
+pub const BLOCK_DOWNLOAD_WINDOW: usize = 1_024;
+
+/// Time a peer has to deliver a requested block
+pub const BLOCK_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Header-only block (no transactions), for difficulty
+/// and work calculations over the header chain
+fn header_only(header: BlockHeader) -> Block {
+    let hash = header.hash();
+    Block {
+        header,
+        transactions: Vec::new(),
+        hash,
+    }
+}
+
+/// Number of leading blocks two chains share
+fn common_prefix(a: &[Block], b: &[Block]) -> usize {
+    a.iter()
+        .zip(b)
+        .take_while(|(x, y)| x.hash == y.hash)
+        .count()
+}
+
+pub struct BlockSync {
+    /// Best-work header chain from genesis
+    headers: Vec<Block>,
+    /// Cumulative work up to and including each header
+    work: Vec<BigUint>,
+    /// Requested bodies: hash → (peer, requested at)
+    in_flight: HashMap<Vec<u8>, (SocketAddr, Instant)>,
+    /// Downloaded bodies not yet connected, with the peer that sent them
+    received: HashMap<Vec<u8>, (Block, SocketAddr)>,
+    /// Peers that let a request time out; not asked again
+    stalled: HashSet<SocketAddr>,
+}
+
+impl BlockSync {
+    pub fn new(active: &[Block]) -> Self {
+        let mut sync = Self {
+            headers: Vec::new(),
+            work: Vec::new(),
+            in_flight: HashMap::new(),
+            received: HashMap::new(),
+            stalled: HashSet::new(),
+        };
+        sync.append(active);
+        sync
+    }
+
+    /// Cumulative work of the first `len` headers
+    fn work_at(&self, len: usize) -> BigUint {
+        match len {
+            0 => BigUint::default(),
+            n => self.work[n - 1].clone(),
+        }
+    }
+
+    /// Extend the header chain with already-validated blocks
+    fn append(&mut self, blocks: &[Block]) {
+        for block in blocks {
+            let work = self.work_at(self.headers.len()) + block_work(block);
+            self.headers.push(header_only(block.header.clone()));
+            self.work.push(work);
+        }
+    }
+
+    /// Check a header against the chain tip and append it
+    fn push_header(&mut self, header: BlockHeader) -> Result<(), &'static str> {
+        let parent = &self.headers[self.headers.len() - 1];
+        if header.height != self.headers.len() as u64 || header.prev_hash != parent.hash {
+            return Err("headers not consecutive");
+        }
+
+        let block = header_only(header);
+        if !check_header(&self.headers, &block.header, &block.hash) {
+            return Err("invalid header");
+        }
+
+        self.append(std::slice::from_ref(&block));
+        Ok(())
+    }
+
+    /// Locator for the best header chain, so header
+    /// download continues where the last batch ended
+    pub fn locator(&self) -> Vec<Vec<u8>> {
+        block_locator(&self.headers)
+    }
+
+    /// True once every header has a connected body
+    pub fn is_synced(&self, active: &[Block]) -> bool {
+        common_prefix(active, &self.headers) == self.headers.len()
+    }
+
+    /// Follow the active chain if it overtook the header chain
+    /// (local mining, blocks announced outside sync)
+    ///
+    /// Only the part of `active` off the header chain is summed;
+    /// the header chain's work is kept as a running total.
+    pub fn refresh(&mut self, active: &[Block]) {
+        let shared = common_prefix(active, &self.headers);
+        if shared == active.len() {
+            return;
+        }
+
+        let active_work = self.work_at(shared) + cumulative_work(&active[shared..]);
+        if active_work < self.work_at(self.headers.len()) {
+            return;
+        }
+
+        self.headers.truncate(shared);
+        self.work.truncate(shared);
+        self.append(&active[shared..]);
+        self.in_flight.clear();
+        self.received.clear();
+    }
+
+    /// Validate a Headers batch and adopt it if it makes a
+    /// chain with more work than the current best
+    ///
+    /// Returns the number of headers adopted.
+    pub fn on_headers(
+        &mut self,
+        active: &[Block],
+        headers: Vec<BlockHeader>,
+    ) -> Result<usize, &'static str> {
+        self.refresh(active);
+
+        let first = match headers.first() {
+            Some(h) => h,
+            None => return Ok(0),
+        };
+
+        let fork = self
+            .headers
+            .iter()
+            .position(|b| b.hash == first.prev_hash)
+            .ok_or("headers do not connect")?
+            + 1;
+
+        // Validate in place on top of the fork point; the old
+        // suffix is put back if the batch does not win
+        let best_work = self.work_at(self.headers.len());
+        let displaced = self.headers.split_off(fork);
+        let displaced_work = self.work.split_off(fork);
+
+        let count = headers.len();
+        let result = headers.into_iter().try_for_each(|h| self.push_header(h));
+
+        if result.is_err() || self.work_at(self.headers.len()) <= best_work {
+            self.headers.truncate(fork);
+            self.work.truncate(fork);
+            self.headers.extend(displaced);
+            self.work.extend(displaced_work);
+            return result.map(|_| 0);
+        }
+
+        let shared = common_prefix(&self.headers[fork..], &displaced);
+
+        // Requests for blocks off the new best chain are moot
+        for block in &displaced[shared..] {
+            self.in_flight.remove(&block.hash);
+            self.received.remove(&block.hash);
+        }
+
+        Ok(count - shared)
+    }
+
+    /// Assign missing bodies to peers with free slots
+    ///
+    /// Returns the GetData batch to send to each peer.
+    pub fn request_blocks(
+        &mut self,
+        active: &[Block],
+        peers: &[SocketAddr],
+        now: Instant,
+    ) -> Vec<(SocketAddr, Vec<InvItem>)> {
+        self.refresh(active);
+
+        let fork = common_prefix(active, &self.headers);
+
+        let mut load: HashMap<SocketAddr, usize> = HashMap::new();
+        for (peer, _) in self.in_flight.values() {
+            *load.entry(*peer).or_default() += 1;
+        }
+
+        let mut available: Vec<SocketAddr> = peers
+            .iter()
+            .filter(|p| !self.stalled.contains(p))
+            .copied()
+            .collect();
+
+        let mut batches: HashMap<SocketAddr, Vec<InvItem>> = HashMap::new();
+        let mut next = 0;
+
+        for block in self.headers.iter().skip(fork).take(BLOCK_DOWNLOAD_WINDOW) {
+            if self.in_flight.contains_key(&block.hash) || self.received.contains_key(&block.hash) {
+                continue;
+            }
+
+            // Round-robin over peers with capacity left
+            available.retain(|p| load.get(p).copied().unwrap_or(0) < MAX_BLOCKS_IN_FLIGHT_PER_PEER);
+            if available.is_empty() {
+                break;
+            }
+
+            let peer = available[next % available.len()];
+            next += 1;
+
+            *load.entry(peer).or_default() += 1;
+            self.in_flight.insert(block.hash.clone(), (peer, now));
+            batches.entry(peer).or_default().push(InvItem::block(&block.hash));
+        }
+
+        batches.into_iter().collect()
+    }
+
+    /// Take a downloaded body sent by `from`
+    ///
+    /// Returns false if it is not on the best header chain
+    /// (an unsolicited block for the caller to handle).
+    pub fn on_block(&mut self, block: Block, from: SocketAddr) -> bool {
+        self.in_flight.remove(&block.hash);
+
+        if !self.headers.iter().any(|b| b.hash == block.hash) {
+            return false;
+        }
+
+        self.received.insert(block.hash.clone(), (block, from));
+        true
+    }
+
+    /// Downloaded bodies that connect to the active chain
+    ///
+    /// A run is released only once it would out-work the active
+    /// chain (or reaches the best header), so a reorg is handed
+    /// over in one piece. Each block comes with the peer that
+    /// sent it, so an invalid one can be blamed.
+    pub fn next_branch(&mut self, active: &[Block]) -> Option<Vec<(Block, SocketAddr)>> {
+        let fork = common_prefix(active, &self.headers);
+
+        let run = self.headers[fork..]
+            .iter()
+            .take_while(|b| self.received.contains_key(&b.hash))
+            .count();
+
+        if run == 0 {
+            return None;
+        }
+
+        let end = fork + run;
+        if end < self.headers.len()
+            && self.work_at(end) <= self.work_at(fork) + cumulative_work(&active[fork..])
+        {
+            return None;
+        }
+
+        Some(
+            self.headers[fork..end]
+                .iter()
+                .filter_map(|b| self.received.remove(&b.hash))
+                .collect(),
+        )
+    }
+
+    /// Drop a header chain whose bodies failed validation
+    pub fn reset(&mut self, active: &[Block]) {
+        *self = Self {
+            stalled: std::mem::take(&mut self.stalled),
+            ..Self::new(active)
+        };
+    }
+
+    /// Peers whose oldest request timed out
+    ///
+    /// Their requests are released for reassignment and they
+    /// are not used again for block download.
+    pub fn stalled_peers(&mut self, now: Instant) -> Vec<SocketAddr> {
+        let stalled: HashSet<SocketAddr> = self
+            .in_flight
+            .values()
+            .filter(|(_, at)| now.duration_since(*at) > BLOCK_STALL_TIMEOUT)
+            .map(|(peer, _)| *peer)
+            .collect();
+
+        self.in_flight.retain(|_, (peer, _)| !stalled.contains(peer));
+        self.stalled.extend(stalled.iter().copied());
+        stalled.into_iter().collect()
+    }
+
+    /// Forget a disconnected peer and release its requests
+    pub fn remove_peer(&mut self, peer: &SocketAddr) {
+        self.in_flight.retain(|_, (p, _)| p != peer);
+        self.stalled.remove(peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::params::{MAX_TARGET, TARGET_BLOCK_TIME};
+
+    /// Next block on `chain`; `skew` seconds late to tell forks apart
+    fn mine(chain: &[Block], skew: i64) -> Block {
+        header_only(BlockHeader {
+            version: 0,
+            height: chain.len() as u64,
+            timestamp: 1_600_000_000 + chain.len() as i64 * TARGET_BLOCK_TIME + skew,
+            prev_hash: chain.last().map(|b| b.hash.clone()).unwrap_or_default(),
+            nonce: 0,
+            target: MAX_TARGET,
+            merkle_root: Vec::new(),
+        })
+    }
+
+    /// `chain` extended by `n` blocks
+    fn extend(chain: &[Block], n: usize, skew: i64) -> Vec<Block> {
+        let mut blocks = chain.to_vec();
+        for _ in 0..n {
+            let block = mine(&blocks, skew);
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    fn headers(blocks: &[Block]) -> Vec<BlockHeader> {
+        blocks.iter().map(|b| b.header.clone()).collect()
+    }
+
+    fn peer(port: u16) -> SocketAddr {
+        SocketAddr::from(([10, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn adopts_headers_with_more_work() {
+        let active = extend(&[], 3, 0);
+        let best = extend(&active, 5, 0);
+        let mut sync = BlockSync::new(&active);
+
+        assert_eq!(sync.on_headers(&active, headers(&best[3..])), Ok(5));
+        assert_eq!(sync.locator()[0], best[7].hash);
+        assert!(!sync.is_synced(&active));
+
+        // Resending the same batch changes nothing
+        assert_eq!(sync.on_headers(&active, headers(&best[3..])), Ok(0));
+    }
+
+    #[test]
+    fn switches_to_heavier_fork_and_keeps_lighter_one_out() {
+        let active = extend(&[], 3, 0);
+        let first = extend(&active, 2, 0);
+        let heavier = extend(&active, 4, 1);
+        let lighter = extend(&active, 1, 2);
+        let mut sync = BlockSync::new(&active);
+
+        assert_eq!(sync.on_headers(&active, headers(&first[3..])), Ok(2));
+        assert_eq!(sync.on_headers(&active, headers(&lighter[3..])), Ok(0));
+        assert_eq!(sync.locator()[0], first[4].hash);
+
+        assert_eq!(sync.on_headers(&active, headers(&heavier[3..])), Ok(4));
+        assert_eq!(sync.locator()[0], heavier[6].hash);
+    }
+
+    #[test]
+    fn rejected_batch_leaves_header_chain_intact() {
+        let active = extend(&[], 3, 0);
+        let best = extend(&active, 3, 0);
+        let mut sync = BlockSync::new(&active);
+        sync.on_headers(&active, headers(&best[3..])).unwrap();
+
+        let mut bad = headers(&extend(&active, 4, 1)[3..]);
+        bad[2].target = [0x7f; 32];
+        assert_eq!(sync.on_headers(&active, bad), Err("invalid header"));
+
+        let mut gap = headers(&extend(&active, 3, 1)[3..]);
+        gap.remove(1);
+        assert_eq!(sync.on_headers(&active, gap), Err("headers not consecutive"));
+
+        let orphan = headers(&extend(&[], 2, 5)[1..]);
+        assert_eq!(sync.on_headers(&active, orphan), Err("headers do not connect"));
+
+        assert_eq!(sync.locator()[0], best[5].hash);
+    }
+
+    #[test]
+    fn follows_active_chain_that_overtakes_headers() {
+        let active = extend(&[], 3, 0);
+        let best = extend(&active, 2, 0);
+        let mut sync = BlockSync::new(&active);
+        sync.on_headers(&active, headers(&best[3..])).unwrap();
+
+        let mined = extend(&active, 3, 1);
+        sync.refresh(&mined);
+        assert_eq!(sync.locator()[0], mined[5].hash);
+        assert!(sync.is_synced(&mined));
+    }
+
+    #[test]
+    fn locator_is_dense_then_exponential() {
+        let active = extend(&[], 40, 0);
+        let sync = BlockSync::new(&active);
+
+        let heights: Vec<usize> = sync
+            .locator()
+            .iter()
+            .map(|h| active.iter().position(|b| &b.hash == h).unwrap())
+            .collect();
+
+        assert_eq!(heights, vec![39, 38, 37, 36, 35, 34, 33, 32, 31, 30, 28, 24, 16, 0]);
+    }
+
+    #[test]
+    fn stalled_peer_loses_its_requests() {
+        let active = extend(&[], 2, 0);
+        let best = extend(&active, 4, 0);
+        let mut sync = BlockSync::new(&active);
+        sync.on_headers(&active, headers(&best[2..])).unwrap();
+
+        let start = Instant::now();
+        let (slow, fast) = (peer(1), peer(2));
+        let batches = sync.request_blocks(&active, &[slow, fast], start);
+        assert_eq!(batches.iter().map(|(_, items)| items.len()).sum::<usize>(), 4);
+
+        // Nothing stalls before the timeout
+        assert!(sync.stalled_peers(start + BLOCK_STALL_TIMEOUT).is_empty());
+
+        // The fast peer delivers, the slow one does not
+        let (_, delivered) = batches.iter().find(|(p, _)| *p == fast).unwrap();
+        for item in delivered {
+            let block = best.iter().find(|b| b.hash == item.hash).unwrap();
+            sync.on_block(block.clone(), fast);
+        }
+
+        let later = start + BLOCK_STALL_TIMEOUT + Duration::from_secs(1);
+        assert_eq!(sync.stalled_peers(later), vec![slow]);
+
+        // Its blocks go to the remaining peer only
+        let retry = sync.request_blocks(&active, &[slow, fast], later);
+        assert_eq!(retry.len(), 1);
+        assert_eq!(retry[0].0, fast);
+        assert_eq!(retry[0].1.len(), 2);
+    }
+
+    #[test]
+    fn branch_is_released_with_its_senders() {
+        let active = extend(&[], 2, 0);
+        let best = extend(&active, 2, 0);
+        let mut sync = BlockSync::new(&active);
+        sync.on_headers(&active, headers(&best[2..])).unwrap();
+
+        assert!(sync.on_block(best[3].clone(), peer(2)));
+        assert!(sync.next_branch(&active).is_none());
+
+        assert!(sync.on_block(best[2].clone(), peer(1)));
+        assert!(!sync.on_block(extend(&active, 1, 1)[2].clone(), peer(1)));
+
+        let branch = sync.next_branch(&active).unwrap();
+        let senders: Vec<SocketAddr> = branch.iter().map(|(_, p)| *p).collect();
+        assert_eq!(senders, vec![peer(1), peer(2)]);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::crypto::sha256;
+
+// ───────── Wire framing ─────────
+//
+// Every message on the wire is one frame:
+//
+//   magic     4 bytes   NETWORK_MAGIC
+//   command  12 bytes   ASCII, NUL-padded
+//   length    4 bytes   payload length, u32 LE
+//   checksum  4 bytes   first 4 bytes of sha256(sha256(payload))
+//   payload   `length` bytes (bincode NetworkMessage)
+//
+// Stream transports (TCP, file pipes) reassemble frames
+// with a FrameDecoder; datagram transports (UDP) carry
+// exactly one frame per packet and use `decode`.
+
+/// Identifies our network on the wire
+pub const NETWORK_MAGIC: [u8; 4] = [0xe3, 0x52, 0x45, 0x56];
+
+pub const COMMAND_SIZE: usize = 12;
+pub const HEADER_SIZE: usize = 4 + COMMAND_SIZE + 4 + 4;
+
+/// Largest payload accepted (a full block plus encoding overhead)
+pub const MAX_PAYLOAD_SIZE: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub command: String,
+    pub payload: Vec<u8>,
+}
+
+fn checksum(payload: &[u8]) -> [u8; 4] {
+    let h = sha256(&sha256(payload));
+    [h[0], h[1], h[2], h[3]]
+}
+
+/// Build a frame for `payload`
+///
+/// `command` is truncated to COMMAND_SIZE bytes.
+pub fn encode(command: &str, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_SIZE + payload.len());
+
+    out.extend_from_slice(&NETWORK_MAGIC);
+
+    let mut cmd = [0u8; COMMAND_SIZE];
+    let n = command.len().min(COMMAND_SIZE);
+    cmd[..n].copy_from_slice(&command.as_bytes()[..n]);
+    out.extend_from_slice(&cmd);
+
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(&checksum(payload));
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Parse a frame header: (command, payload length, checksum)
+fn parse_header(header: &[u8]) -> Result<(String, usize, [u8; 4]), &'static str> {
+    if header[..4] != NETWORK_MAGIC {
+        return Err("bad network magic");
+    }
+
+    let cmd = &header[4..4 + COMMAND_SIZE];
+    let end = cmd.iter().position(|&b| b == 0).unwrap_or(COMMAND_SIZE);
+    if cmd[end..].iter().any(|&b| b != 0) || !cmd[..end].is_ascii() {
+        return Err("malformed command");
+    }
+    let command = String::from_utf8_lossy(&cmd[..end]).into_owned();
+
+    let len_at = 4 + COMMAND_SIZE;
+    let len = u32::from_le_bytes(header[len_at..len_at + 4].try_into().unwrap()) as usize;
+    if len > MAX_PAYLOAD_SIZE {
+        return Err("payload too large");
+    }
+
+    let sum: [u8; 4] = header[len_at + 4..HEADER_SIZE].try_into().unwrap();
+    Ok((command, len, sum))
+}
+
+/// Decode exactly one frame (one datagram)
+pub fn decode(data: &[u8]) -> Result<Frame, &'static str> {
+    if data.len() < HEADER_SIZE {
+        return Err("truncated frame");
+    }
+
+    let (command, len, sum) = parse_header(&data[..HEADER_SIZE])?;
+
+    if data.len() != HEADER_SIZE + len {
+        return Err("length mismatch");
+    }
+
+    let payload = &data[HEADER_SIZE..];
+    if checksum(payload) != sum {
+        return Err("bad checksum");
+    }
+
+    Ok(Frame {
+        command,
+        payload: payload.to_vec(),
+    })
+}
+
+/// Per-connection reassembly buffer for stream transports
+///
+/// Reads may split or coalesce frames; push raw bytes as they
+/// arrive and pop complete frames. After an error the stream
+/// cannot be resynchronised and the connection should be dropped.
+#[derive(Default)]
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Next complete frame as raw wire bytes, if buffered
+    ///
+    /// The returned bytes are a whole frame, ready for `decode`.
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, &'static str> {
+        if self.buf.len() < HEADER_SIZE {
+            return Ok(None);
+        }
+
+        let (_, len, _) = parse_header(&self.buf[..HEADER_SIZE])?;
+
+        if self.buf.len() < HEADER_SIZE + len {
+            return Ok(None);
+        }
+
+        let rest = self.buf.split_off(HEADER_SIZE + len);
+        let frame = std::mem::replace(&mut self.buf, rest);
+
+        // Checksum verified here so corruption drops the stream
+        decode(&frame)?;
+        Ok(Some(frame))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_split_across_reads() {
+        let wire = encode("block", b"payload bytes");
+        let mut decoder = FrameDecoder::new();
+
+        for byte in &wire[..wire.len() - 1] {
+            decoder.push(std::slice::from_ref(byte));
+            assert_eq!(decoder.next_frame(), Ok(None));
+        }
+
+        decoder.push(&wire[wire.len() - 1..]);
+        assert_eq!(decoder.next_frame(), Ok(Some(wire.clone())));
+        assert_eq!(decoder.next_frame(), Ok(None));
+
+        let frame = decode(&wire).unwrap();
+        assert_eq!(frame.command, "block");
+        assert_eq!(frame.payload, b"payload bytes");
+    }
+
+    #[test]
+    fn frames_coalesced_in_one_read() {
+        let first = encode("ping", b"");
+        let second = encode("tx", &[7u8; 100]);
+        let third = encode("pong", b"x");
+
+        let mut read = [first.clone(), second.clone()].concat();
+        read.extend_from_slice(&third[..10]);
+
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&read);
+        assert_eq!(decoder.next_frame(), Ok(Some(first)));
+        assert_eq!(decoder.next_frame(), Ok(Some(second)));
+        assert_eq!(decoder.next_frame(), Ok(None));
+
+        decoder.push(&third[10..]);
+        assert_eq!(decoder.next_frame(), Ok(Some(third)));
+    }
+
+    #[test]
+    fn bad_magic_is_rejected() {
+        let mut wire = encode("ping", b"");
+        wire[0] ^= 0xff;
+
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&wire);
+        assert_eq!(decoder.next_frame(), Err("bad network magic"));
+        assert_eq!(decode(&wire).unwrap_err(), "bad network magic");
+    }
+
+    #[test]
+    fn bad_checksum_is_rejected() {
+        let mut wire = encode("tx", b"some payload");
+        let last = wire.len() - 1;
+        wire[last] ^= 0x01;
+
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&wire);
+        assert_eq!(decoder.next_frame(), Err("bad checksum"));
+        assert_eq!(decode(&wire).unwrap_err(), "bad checksum");
+    }
+
+    #[test]
+    fn oversized_payload_is_rejected_from_the_header() {
+        let at_limit = encode("block", &vec![0u8; MAX_PAYLOAD_SIZE]);
+        assert!(decode(&at_limit).is_ok());
+
+        // Only the header is needed to refuse an oversized frame
+        let mut header = encode("block", b"")[..HEADER_SIZE].to_vec();
+        let len_at = 4 + COMMAND_SIZE;
+        header[len_at..len_at + 4].copy_from_slice(&(MAX_PAYLOAD_SIZE as u32 + 1).to_le_bytes());
+
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&header);
+        assert_eq!(decoder.next_frame(), Err("payload too large"));
+    }
+}
@@ -7,7 +7,7 @@ use std::time::Duration;
 ///
 /// Uses UDP broadcast to:
 /// - Discover peers on the local network
-/// - Exchange raw Bitcoin P2P messages
+/// - Exchange framed Bitcoin P2P messages (one per datagram)
 ///
 /// This transport is:
 /// - Internet-independent
@@ -1,5 +1,8 @@
 use std::net::SocketAddr;
 
+// ───────── Wire format ─────────
+pub mod frame;
+
 // ───────── Transport implementations ─────────
 pub mod tcp;
 pub mod bluetooth;
@@ -7,9 +10,21 @@ pub mod satellite;
 pub mod geo;
 pub mod offline;
 
+// ───────── Connection events ─────────
+#[derive(Debug, Clone, Copy)]
+pub enum PeerEvent {
+    Connected { addr: SocketAddr, outbound: bool },
+    Disconnected(SocketAddr),
+}
+
+pub type OnPeerEvent = std::sync::Arc<dyn Fn(PeerEvent) + Send + Sync>;
+
 // ───────── Transport trait ─────────
 pub trait Transport: Send + Sync {
     fn send(&self, addr: &SocketAddr, data: &[u8]);
     fn broadcast(&self, data: &[u8]);
     fn peers(&self) -> Vec<SocketAddr>;
+    fn disconnect(&self, addr: &SocketAddr);
+    /// Open an outbound connection (blocking); false on failure
+    fn connect(&self, addr: &SocketAddr) -> bool;
 }
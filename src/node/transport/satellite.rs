@@ -5,12 +5,17 @@ use std::io::{Read};
 use std::fs::File;
 use std::time::Duration;
 
+use crate::node::transport::frame::FrameDecoder;
+
 /// Receive-only satellite transport
 ///
-/// This transport ingests raw NetworkMessage bytes from an
+/// This transport ingests framed NetworkMessage bytes from an
 /// external satellite decoder (UDP or file pipe) and injects
 /// them into the normal P2P message handler.
 ///
+/// UDP: one frame per datagram. File pipe: a byte stream,
+/// reassembled into frames.
+///
 /// Consensus rules are NOT bypassed.
 /// Validation remains identical to TCP/Bluetooth/etc.
 pub struct SatelliteTransport {
@@ -70,6 +75,10 @@ impl SatelliteTransport {
 
         thread::spawn(move || {
             let mut buf = vec![0u8; 1024 * 1024];
+            let mut decoder = FrameDecoder::new();
+
+            // Use a dummy address to represent satellite source
+            let sat_addr: SocketAddr = "0.0.0.0:0".parse().unwrap();
 
             loop {
                 match file.read(&mut buf) {
@@ -78,9 +87,20 @@ impl SatelliteTransport {
                         thread::sleep(Duration::from_millis(200));
                     }
                     Ok(n) => {
-                        // Use a dummy address to represent satellite source
-                        let sat_addr: SocketAddr = "0.0.0.0:0".parse().unwrap();
-                        (on_receive)(sat_addr, buf[..n].to_vec());
+                        decoder.push(&buf[..n]);
+
+                        loop {
+                            match decoder.next_frame() {
+                                Ok(Some(frame)) => (on_receive)(sat_addr, frame),
+                                Ok(None) => break,
+                                Err(e) => {
+                                    // No resync point in a corrupt stream
+                                    println!("🛰 Satellite stream corrupt ({}); resetting", e);
+                                    decoder = FrameDecoder::new();
+                                    break;
+                                }
+                            }
+                        }
                     }
                     Err(_) => {
                         thread::sleep(Duration::from_millis(500));
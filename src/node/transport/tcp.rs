@@ -1,84 +1,259 @@
 use std::collections::HashMap;
-use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream, SocketAddr};
-use std::sync::{Arc, Mutex};
+use std::net::SocketAddr;
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
-use crate::node::transport::Transport;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc::{channel, error::TrySendError, Receiver, Sender};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
 
-const MAX_MESSAGE_SIZE: usize = 1 * 1024 * 1024;
+use crate::node::transport::{OnPeerEvent, PeerEvent, Transport};
+use crate::node::transport::frame::FrameDecoder;
 
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Frames queued per peer before it counts as too slow
+pub const PEER_SEND_QUEUE: usize = 256;
+
+/// Time allowed to establish an outbound connection
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+type OnReceive = Arc<dyn Fn(SocketAddr, Vec<u8>) + Send + Sync>;
+
+/// Peer event, plus a signal to send once it has been handled
+type QueuedEvent = (PeerEvent, Option<oneshot::Sender<()>>);
+type Events = mpsc::Sender<QueuedEvent>;
+
+struct Peer {
+    /// Frames waiting for the writer task
+    queue: Sender<Vec<u8>>,
+    reader: JoinHandle<()>,
+}
+
+type Peers = Arc<Mutex<HashMap<SocketAddr, Peer>>>;
+
+/// Async TCP transport on its own tokio runtime
+///
+/// Every connection, inbound or outbound, gets a reader task
+/// (frames → `on_receive`) and a writer task draining a bounded
+/// queue. A peer whose queue fills up is not keeping up and is
+/// disconnected rather than buffering without limit.
+///
+/// Peer events are handled in order on a dedicated thread: the
+/// handler takes node locks (the chain, held while mining) and
+/// must not stall the runtime's few workers.
 pub struct TcpTransport {
-    peers: Arc<Mutex<HashMap<SocketAddr, TcpStream>>>,
+    runtime: Runtime,
+    peers: Peers,
+    on_receive: OnReceive,
+    events: Events,
 }
 
 impl TcpTransport {
-    pub fn new(
-        bind: &str,
-        on_receive: Arc<dyn Fn(SocketAddr, Vec<u8>) + Send + Sync>,
-    ) -> Arc<Self> {
-        let listener = TcpListener::bind(bind).expect("TCP bind failed");
-        listener.set_nonblocking(true).unwrap();
-
-        let peers = Arc::new(Mutex::new(HashMap::new()));
-        let peers_accept = Arc::clone(&peers);
-        let on_receive = Arc::clone(&on_receive);
-
-        thread::spawn(move || loop {
-            match listener.accept() {
-                Ok((mut stream, addr)) => {
-                    stream
-                        .set_read_timeout(Some(Duration::from_secs(30)))
-                        .ok();
-
-                    peers_accept
-                        .lock()
-                        .unwrap()
-                        .insert(addr, stream.try_clone().unwrap());
-
-                    let peers_inner = Arc::clone(&peers_accept);
-                    let on_receive = Arc::clone(&on_receive);
-
-                    thread::spawn(move || {
-                        let mut buf = vec![0u8; MAX_MESSAGE_SIZE];
-                        loop {
-                            match stream.read(&mut buf) {
-                                Ok(0) | Err(_) => break,
-                                Ok(n) => (on_receive)(addr, buf[..n].to_vec()),
-                            }
-                        }
-                        peers_inner.lock().unwrap().remove(&addr);
-                    });
+    /// Listen for inbound peers on `bind` (port 0 = outbound only)
+    pub fn new(bind: &str, on_receive: OnReceive, on_event: OnPeerEvent) -> Arc<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("p2p-tcp")
+            .enable_all()
+            .build()
+            .expect("Tokio runtime failed");
+
+        let listener = runtime
+            .block_on(TcpListener::bind(bind))
+            .expect("TCP bind failed");
+
+        let (events, queued) = mpsc::channel::<QueuedEvent>();
+
+        thread::Builder::new()
+            .name("p2p-events".into())
+            .spawn(move || {
+                for (event, handled) in queued {
+                    (on_event)(event);
+                    if let Some(handled) = handled {
+                        let _ = handled.send(());
+                    }
+                }
+            })
+            .expect("P2P event thread failed");
+
+        let transport = Arc::new(Self {
+            runtime,
+            peers: Arc::new(Mutex::new(HashMap::new())),
+            on_receive,
+            events,
+        });
+
+        let peers = Arc::clone(&transport.peers);
+        let on_receive = Arc::clone(&transport.on_receive);
+        let events = transport.events.clone();
+
+        transport.runtime.spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, addr)) => register(
+                        stream,
+                        addr,
+                        false,
+                        Arc::clone(&peers),
+                        Arc::clone(&on_receive),
+                        events.clone(),
+                    ),
+                    Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
                 }
-                Err(_) => thread::sleep(Duration::from_millis(50)),
             }
         });
 
-        Arc::new(Self { peers })
+        transport
+    }
+}
+
+/// Start reader and writer tasks for a new connection
+fn register(
+    stream: TcpStream,
+    addr: SocketAddr,
+    outbound: bool,
+    peers: Peers,
+    on_receive: OnReceive,
+    events: Events,
+) {
+    let _ = stream.set_nodelay(true);
+    let (read_half, write_half) = stream.into_split();
+    let (queue, pending) = channel(PEER_SEND_QUEUE);
+
+    tokio::spawn(write_loop(write_half, pending));
+
+    // Reading starts only after the Connected event has been
+    // handled, so the handler knows the peer before its first frame
+    let (start, started) = oneshot::channel();
+    let reader = tokio::spawn(read_loop(
+        read_half,
+        addr,
+        started,
+        Arc::clone(&peers),
+        on_receive,
+        events.clone(),
+    ));
+
+    peers.lock().unwrap().insert(addr, Peer { queue, reader });
+    let _ = events.send((PeerEvent::Connected { addr, outbound }, Some(start)));
+}
+
+/// Frames from the peer until EOF, error or a corrupt frame
+async fn read_loop(
+    mut stream: OwnedReadHalf,
+    addr: SocketAddr,
+    started: oneshot::Receiver<()>,
+    peers: Peers,
+    on_receive: OnReceive,
+    events: Events,
+) {
+    if started.await.is_err() {
+        return;
     }
 
-    pub fn connect(&self, addr: SocketAddr) {
-        if let Ok(stream) = TcpStream::connect(addr) {
-            self.peers.lock().unwrap().insert(addr, stream);
+    let mut buf = vec![0u8; READ_CHUNK_SIZE];
+    let mut decoder = FrameDecoder::new();
+
+    'read: loop {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => decoder.push(&buf[..n]),
+        }
+
+        loop {
+            match decoder.next_frame() {
+                // Message handling takes locks and validates;
+                // keep it off the async worker's hot path
+                Ok(Some(frame)) => {
+                    tokio::task::block_in_place(|| (on_receive)(addr, frame))
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    println!("> [WARN] Dropping {}: {}", addr, e);
+                    break 'read;
+                }
+            }
+        }
+    }
+
+    if peers.lock().unwrap().remove(&addr).is_some() {
+        let _ = events.send((PeerEvent::Disconnected(addr), None));
+    }
+}
+
+/// Drain the send queue; ends when the peer is dropped
+async fn write_loop(mut stream: OwnedWriteHalf, mut pending: Receiver<Vec<u8>>) {
+    while let Some(frame) = pending.recv().await {
+        if stream.write_all(&frame).await.is_err() {
+            break;
         }
     }
+    let _ = stream.shutdown().await;
 }
 
 impl Transport for TcpTransport {
     fn send(&self, addr: &SocketAddr, data: &[u8]) {
-        if let Some(s) = self.peers.lock().unwrap().get_mut(addr) {
-            let _ = s.write_all(data);
+        let result = match self.peers.lock().unwrap().get(addr) {
+            Some(peer) => peer.queue.try_send(data.to_vec()),
+            None => return,
+        };
+
+        if let Err(TrySendError::Full(_)) = result {
+            println!("> [WARN] Send queue full for {}. Disconnecting.", addr);
+            self.disconnect(addr);
         }
     }
 
     fn broadcast(&self, data: &[u8]) {
-        for s in self.peers.lock().unwrap().values_mut() {
-            let _ = s.write_all(data);
+        for addr in self.peers() {
+            self.send(&addr, data);
         }
     }
 
     fn peers(&self) -> Vec<SocketAddr> {
         self.peers.lock().unwrap().keys().cloned().collect()
     }
+
+    fn disconnect(&self, addr: &SocketAddr) {
+        let peer = self.peers.lock().unwrap().remove(addr);
+
+        // Dropping the queue ends the writer, which closes the socket
+        if let Some(peer) = peer {
+            peer.reader.abort();
+            let _ = self.events.send((PeerEvent::Disconnected(*addr), None));
+        }
+    }
+
+    /// Blocks until connected or CONNECT_TIMEOUT; call from
+    /// a plain thread, not from inside the runtime.
+    fn connect(&self, addr: &SocketAddr) -> bool {
+        let addr = *addr;
+        if self.peers.lock().unwrap().contains_key(&addr) {
+            return true;
+        }
+
+        let (done_tx, done_rx) = mpsc::channel();
+        let peers = Arc::clone(&self.peers);
+        let on_receive = Arc::clone(&self.on_receive);
+        let events = self.events.clone();
+
+        self.runtime.spawn(async move {
+            let ok = match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(addr)).await {
+                Ok(Ok(stream)) => {
+                    register(stream, addr, true, peers, on_receive, events);
+                    true
+                }
+                _ => false,
+            };
+            let _ = done_tx.send(ok);
+        });
+
+        done_rx.recv().unwrap_or(false)
+    }
 }
@@ -0,0 +1,70 @@
+use serde::{Serialize, Deserialize};
+
+use crate::core::block::{Block, BlockHeader};
+use crate::core::transaction::{Transaction, OutputKind};
+use crate::core::merkle::{merkle_branch, root_from_branch};
+use crate::pow::valid_pow;
+
+/// Document timestamping (NON-CONSENSUS)
+///
+/// A document hash is committed in a data-carrier output.
+/// Once mined, the proof below ties it to a block header:
+///
+///   document hash → tx → merkle path → header → PoW
+///
+/// Third parties can check it offline, without a node,
+/// given a header they trust to be in the best chain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub header: BlockHeader,
+    pub block_hash: Vec<u8>,
+    pub tx: Transaction,
+    pub tx_index: u32,
+    pub merkle_branch: Vec<Vec<u8>>,
+}
+
+impl InclusionProof {
+    /// Verify the proof for `document_hash`
+    ///
+    /// Checks header hash & PoW, that the transaction carries
+    /// the document hash, and that the merkle path reaches
+    /// the header's merkle root.
+    pub fn verify(&self, document_hash: &[u8]) -> bool {
+        if self.header.hash() != self.block_hash
+            || !valid_pow(&self.block_hash, &self.header.target)
+        {
+            return false;
+        }
+
+        let carries_hash = self.tx.outputs.iter().any(|o| {
+            matches!(&o.kind, OutputKind::DataCarrier { data } if data == document_hash)
+        });
+
+        if !carries_hash {
+            return false;
+        }
+
+        root_from_branch(
+            &self.tx.txid(),
+            self.tx_index as usize,
+            &self.merkle_branch,
+        ) == self.header.merkle_root
+    }
+}
+
+/// Build a proof for a confirmed transaction
+pub fn inclusion_proof(blocks: &[Block], txid: &[u8]) -> Option<InclusionProof> {
+    for block in blocks.iter().rev() {
+        if let Some(index) = block.transactions.iter().position(|t| t.txid() == txid) {
+            return Some(InclusionProof {
+                header: block.header.clone(),
+                block_hash: block.hash.clone(),
+                tx: block.transactions[index].clone(),
+                tx_index: index as u32,
+                merkle_branch: merkle_branch(&block.transactions, index),
+            });
+        }
+    }
+
+    None
+}
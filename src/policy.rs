@@ -1,3 +1,9 @@
+use serde::{Serialize, Deserialize};
+
+use crate::core::transaction::{
+    Transaction, OutputKind, TX_VERSION_LEGACY, TX_VERSION_LOCKTIME,
+};
+
 /// Policy limits (NOT consensus yet)
 pub const MAX_BLOCK_SIZE: usize = 1_000_000; // 1 MB
 pub const MAX_BLOCK_TXS: usize = 2_000;
@@ -7,3 +13,220 @@ pub const MAX_BLOCK_TX_BYTES: usize = MAX_BLOCK_SIZE - 1_000;
 
 /// Mempool policy
 pub const MAX_TX_SIZE: usize = 100_000; // 100 KB
+
+/// Data-carrier outputs (relay & mining policy)
+pub const MAX_DATA_CARRIER_BYTES: usize = 80;
+pub const MAX_DATA_CARRIER_OUTPUTS: usize = 1;
+
+/// Unconfirmed chains (mempool policy)
+///
+/// Counts and sizes include the transaction itself.
+pub const MAX_ANCESTORS: usize = 25;
+pub const MAX_DESCENDANTS: usize = 25;
+pub const MAX_ANCESTOR_SIZE: usize = 101_000;
+pub const MAX_DESCENDANT_SIZE: usize = 101_000;
+
+/// Replace-by-fee (mempool policy)
+///
+/// A replacement's extra fee must cover relaying itself and
+/// every evicted descendant at this rate.
+pub const INCREMENTAL_RELAY_FEE_PER_BYTE: i64 = 1;
+pub const MAX_REPLACEMENT_EVICTIONS: usize = 100;
+
+/// Mempool persistence
+///
+/// Entries older than this are dropped when reloaded.
+pub const MEMPOOL_EXPIRY_SECS: i64 = 14 * 24 * 60 * 60; // 2 weeks
+pub const MEMPOOL_SAVE_INTERVAL_SECS: u64 = 10 * 60;
+
+/// Orphan transactions (parents not yet seen)
+pub const MAX_ORPHAN_TXS: usize = 100;
+pub const MAX_ORPHANS_PER_PEER: usize = 25;
+pub const ORPHAN_EXPIRY_SECS: i64 = 20 * 60;
+
+/* ───────── Standardness ───────── */
+
+/// Bytes to create / later spend a single-key output
+/// (matches Transaction::serialized_size)
+const OUTPUT_BYTES: u64 = 34;
+const SPEND_INPUT_BYTES: u64 = 148;
+
+/// Per-node relay & mining policy (NOT consensus)
+///
+/// Read from `policy` in miner_config.json; missing fields
+/// take the defaults below. Blocks from other miners are
+/// never checked against this.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PolicyConfig {
+    /// Minimum fee rate to relay or mine a transaction
+    pub min_relay_fee_per_byte: i64,
+    /// Outputs costing more than their value to spend at this
+    /// rate are dust
+    pub dust_relay_fee_per_byte: u64,
+    pub max_standard_outputs: usize,
+    pub max_data_carrier_bytes: usize,
+    pub max_data_carrier_outputs: usize,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            min_relay_fee_per_byte: 1,
+            dust_relay_fee_per_byte: 3,
+            max_standard_outputs: 100,
+            max_data_carrier_bytes: MAX_DATA_CARRIER_BYTES,
+            max_data_carrier_outputs: MAX_DATA_CARRIER_OUTPUTS,
+        }
+    }
+}
+
+impl PolicyConfig {
+    /// Smallest non-dust output value (546 by default)
+    pub fn dust_threshold(&self) -> u64 {
+        (OUTPUT_BYTES + SPEND_INPUT_BYTES) * self.dust_relay_fee_per_byte
+    }
+
+    /// Minimum fee for a transaction of `size` bytes
+    pub fn min_relay_fee(&self, size: usize) -> i64 {
+        self.min_relay_fee_per_byte * size as i64
+    }
+}
+
+/// Standard transaction shape (relay & block assembly)
+///
+/// - known format version, size <= MAX_TX_SIZE
+/// - 1..=max_standard_outputs outputs
+/// - pubkey-hash outputs lock to a 32-byte hash
+/// - spendable outputs are not dust
+/// - data carriers: zero-value, few, small
+pub fn is_standard(tx: &Transaction, config: &PolicyConfig) -> Result<(), &'static str> {
+    if tx.version != TX_VERSION_LEGACY && tx.version != TX_VERSION_LOCKTIME {
+        return Err("unknown transaction version");
+    }
+
+    if tx.serialized_size() > MAX_TX_SIZE {
+        return Err("transaction too large");
+    }
+
+    if tx.outputs.is_empty() || tx.outputs.len() > config.max_standard_outputs {
+        return Err("bad output count");
+    }
+
+    let mut data_outputs = 0;
+
+    for o in &tx.outputs {
+        match &o.kind {
+            OutputKind::DataCarrier { data } => {
+                data_outputs += 1;
+                if o.value != 0 || data.len() > config.max_data_carrier_bytes {
+                    return Err("non-standard data carrier");
+                }
+            }
+            kind => {
+                if *kind == OutputKind::PubkeyHash && o.pubkey_hash.len() != 32 {
+                    return Err("pubkey_hash must be 32 bytes");
+                }
+                if o.value < config.dust_threshold() {
+                    return Err("dust output");
+                }
+            }
+        }
+    }
+
+    if data_outputs > config.max_data_carrier_outputs {
+        return Err("too many data carriers");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MinerConfig;
+    use crate::core::transaction::TxOutput;
+
+    fn paying(outputs: Vec<TxOutput>) -> Transaction {
+        Transaction {
+            version: TX_VERSION_LOCKTIME,
+            inputs: Vec::new(),
+            outputs,
+            lock_time: 0,
+        }
+    }
+
+    fn pay(value: u64) -> TxOutput {
+        TxOutput::pay_to_pubkey_hash(value, vec![1u8; 32])
+    }
+
+    #[test]
+    fn dust_boundary() {
+        let config = PolicyConfig::default();
+        assert_eq!(config.dust_threshold(), (34 + 148) * 3);
+
+        assert!(is_standard(&paying(vec![pay(546)]), &config).is_ok());
+        assert_eq!(is_standard(&paying(vec![pay(545)]), &config), Err("dust output"));
+
+        let custom = PolicyConfig { dust_relay_fee_per_byte: 10, ..PolicyConfig::default() };
+        assert_eq!(custom.dust_threshold(), 1_820);
+        assert!(is_standard(&paying(vec![pay(1_820)]), &custom).is_ok());
+        assert!(is_standard(&paying(vec![pay(1_819)]), &custom).is_err());
+    }
+
+    #[test]
+    fn data_carrier_limits() {
+        let config = PolicyConfig::default();
+        let data = |len: usize| TxOutput::data_carrier(vec![7u8; len]);
+
+        assert!(is_standard(&paying(vec![data(80), pay(1_000)]), &config).is_ok());
+        assert_eq!(
+            is_standard(&paying(vec![data(81)]), &config),
+            Err("non-standard data carrier")
+        );
+        assert_eq!(
+            is_standard(&paying(vec![data(10), data(10)]), &config),
+            Err("too many data carriers")
+        );
+
+        let mut valued = data(10);
+        valued.value = 1_000;
+        assert!(is_standard(&paying(vec![valued]), &config).is_err());
+
+        let larger = PolicyConfig { max_data_carrier_bytes: 120, ..PolicyConfig::default() };
+        assert!(is_standard(&paying(vec![data(81)]), &larger).is_ok());
+    }
+
+    #[test]
+    fn pubkey_hash_must_be_32_bytes() {
+        let config = PolicyConfig::default();
+        let short = TxOutput::pay_to_pubkey_hash(1_000, vec![1u8; 20]);
+
+        assert_eq!(
+            is_standard(&paying(vec![short]), &config),
+            Err("pubkey_hash must be 32 bytes")
+        );
+    }
+
+    #[test]
+    fn policy_read_from_miner_config() {
+        let config: MinerConfig = serde_json::from_str(
+            r#"{
+                "coinbase_wallet": "default",
+                "policy": { "dust_relay_fee_per_byte": 10, "max_standard_outputs": 1 }
+            }"#,
+        )
+        .unwrap();
+
+        // Unset fields keep their defaults
+        assert_eq!(config.policy.min_relay_fee_per_byte, 1);
+        assert_eq!(config.policy.max_data_carrier_bytes, MAX_DATA_CARRIER_BYTES);
+
+        let tx = paying(vec![pay(1_000)]);
+        assert!(is_standard(&tx, &PolicyConfig::default()).is_ok());
+        assert_eq!(is_standard(&tx, &config.policy), Err("dust output"));
+
+        let two = paying(vec![pay(5_000), pay(5_000)]);
+        assert_eq!(is_standard(&two, &config.policy), Err("bad output count"));
+    }
+}
@@ -3,6 +3,6 @@ pub fn block_reward(height: u64) -> u64 {
     if halvings >= 64 {
         0
     } else {
-        50 * 100_000_000 >> halvings
+        (50 * 100_000_000) >> halvings
     }
 }
@@ -16,14 +16,23 @@ use pbkdf2::pbkdf2_hmac;
 use bip39::{Mnemonic, Language};
 
 use crate::crypto::{
+    sha256,
     secret_key_from_seed,
     public_key,
     pubkey_hash,
     sign,
 };
 
-use crate::core::transaction::{Transaction, TxInput, TxOutput};
-use crate::core::utxo::UTXOSet;
+use serde::{Serialize, Deserialize};
+
+use crate::core::transaction::{
+    Transaction, TxInput, TxOutput, InputSignature, OutputKind,
+    TX_VERSION_LEGACY, TX_VERSION_LOCKTIME, MAX_MULTISIG_KEYS, HTLC_PREIMAGE_SIZE,
+};
+use crate::core::block::Block;
+use crate::core::utxo::{UTXOSet, UTXO, outpoint_key};
+use crate::policy::{INCREMENTAL_RELAY_FEE_PER_BYTE, PolicyConfig};
+use crate::consensus::version::{consensus_version_for_height, ConsensusVersion};
 
 /* ───────── Encrypted Wallet File ───────── */
 
@@ -57,12 +66,52 @@ fn derive_child_seed(master: &[u8; 32], index: u32) -> [u8; 32] {
     out
 }
 
+/// Find the derived key (index 0..20) owning `hash`
+fn key_index_for(master: &[u8; 32], hash: &[u8]) -> Option<u32> {
+    (0..20).find(|index| {
+        let child = derive_child_seed(master, *index);
+        let sk = secret_key_from_seed(&child);
+        pubkey_hash(&public_key(&sk)) == hash
+    })
+}
+
+/* ───────── Fees ───────── */
+
+/// Default fee rate for wallet transactions (POLICY ONLY)
+pub const DEFAULT_FEE_PER_BYTE: u64 = 1;
+
+/// Fee for a single-key transaction of the given shape
+/// (matches Transaction::serialized_size)
+fn estimated_fee(inputs: usize, outputs: usize, fee_per_byte: u64) -> u64 {
+    (inputs * 148 + outputs * 34 + 10) as u64 * fee_per_byte
+}
+
+/// Sign every input of a v6 (non-legacy) transaction
+///
+/// The v6 sighash commits to outpoints but not signatures,
+/// so inputs are filled in first and signed afterwards.
+fn sign_inputs(master: &[u8; 32], tx: &mut Transaction) {
+    let sighash = tx.sighash();
+
+    for input in &mut tx.inputs {
+        let child = derive_child_seed(master, input.address_index);
+        let sk = secret_key_from_seed(&child);
+        let pk = public_key(&sk);
+
+        input.pubkey = pk.serialize().to_vec();
+        input.signature = sign(&sighash, &sk);
+    }
+}
+
 /* ───────── Wallet Struct ───────── */
 
 pub struct Wallet {
     master_seed: Option<[u8; 32]>,
     last_unlock: Option<Instant>,
     next_index: u32,
+    fee_per_byte: u64,
+    /// Node relay policy; change below its dust threshold goes to fees
+    policy: PolicyConfig,
 }
 
 /* ───────── Wallet Impl ───────── */
@@ -76,6 +125,8 @@ impl Wallet {
                 master_seed: None,
                 last_unlock: None,
                 next_index: 0,
+                fee_per_byte: DEFAULT_FEE_PER_BYTE,
+                policy: PolicyConfig::default(),
             };
 
             w.unlock(password).expect("wallet unlock failed");
@@ -148,12 +199,14 @@ impl Wallet {
             master_seed: Some(master_seed),
             last_unlock: Some(Instant::now()),
             next_index: 0,
+            fee_per_byte: DEFAULT_FEE_PER_BYTE,
+            policy: PolicyConfig::default(),
         })
     }
 
-    pub fn unlock(&mut self, password: &str) -> Result<(), ()> {
-        let data = fs::read("data/wallet.dat").map_err(|_| ())?;
-        let wf: WalletFile = bincode::deserialize(&data).map_err(|_| ())?;
+    pub fn unlock(&mut self, password: &str) -> Result<(), &'static str> {
+        let data = fs::read("data/wallet.dat").map_err(|_| "wallet file unreadable")?;
+        let wf: WalletFile = bincode::deserialize(&data).map_err(|_| "wallet file corrupt")?;
 
         let mut enc_key = [0u8; 32];
         pbkdf2_hmac::<Sha256>(
@@ -170,7 +223,7 @@ impl Wallet {
                 GenericArray::from_slice(&wf.nonce),
                 wf.encrypted_master_seed.as_ref(),
             )
-            .map_err(|_| ())?;
+            .map_err(|_| "wrong password")?;
 
         let mut master_seed = [0u8; 32];
         master_seed.copy_from_slice(&seed[..32]);
@@ -183,6 +236,18 @@ impl Wallet {
         Ok(())
     }
 
+    /// Fee rate for transactions built from now on
+    /// (e.g. from `FeeEstimator::estimate`)
+    pub fn set_fee_rate(&mut self, fee_per_byte: u64) {
+        self.fee_per_byte = fee_per_byte.max(DEFAULT_FEE_PER_BYTE);
+    }
+
+    /// Standardness rules the node relays under, so wallet
+    /// transactions never carry outputs it would reject
+    pub fn set_policy(&mut self, policy: PolicyConfig) {
+        self.policy = policy;
+    }
+
     pub fn address(&self) -> Result<Vec<u8>, &'static str> {
         let master = self.master_seed.ok_or("wallet locked")?;
         let child = derive_child_seed(&master, 0);
@@ -196,74 +261,566 @@ impl Wallet {
         utxos: &UTXOSet,
         to: Vec<u8>,
         amount: u64,
-        _current_height: u64,
+        current_height: u64,
+        lock_time: u64,
+    ) -> Result<Transaction, &'static str> {
+        self.create_payment(
+            utxos,
+            TxOutput::pay_to_pubkey_hash(amount, to),
+            current_height,
+            lock_time,
+            self.fee_per_byte,
+        )
+    }
+
+    /// Fund an m-of-n multisig output (CONSENSUS v6)
+    pub fn create_multisig_funding(
+        &mut self,
+        utxos: &UTXOSet,
+        required: u32,
+        pubkey_hashes: Vec<Vec<u8>>,
+        amount: u64,
+        current_height: u64,
+    ) -> Result<Transaction, &'static str> {
+        if required == 0
+            || required as usize > pubkey_hashes.len()
+            || pubkey_hashes.len() > MAX_MULTISIG_KEYS
+        {
+            return Err("invalid m-of-n");
+        }
+
+        self.create_payment(
+            utxos,
+            TxOutput::multisig(amount, required, pubkey_hashes),
+            current_height,
+            0,
+            self.fee_per_byte,
+        )
+    }
+
+    /// Commit `data` (e.g. a document hash) in a
+    /// data-carrier output (CONSENSUS v6)
+    ///
+    /// Pays `fee_per_byte` (at least DEFAULT_FEE_PER_BYTE) for
+    /// this transaction only; the wallet fee rate is unchanged.
+    pub fn create_data_transaction(
+        &mut self,
+        utxos: &UTXOSet,
+        data: Vec<u8>,
+        current_height: u64,
+        fee_per_byte: u64,
+    ) -> Result<Transaction, &'static str> {
+        self.create_payment(
+            utxos,
+            TxOutput::data_carrier(data),
+            current_height,
+            0,
+            fee_per_byte.max(DEFAULT_FEE_PER_BYTE),
+        )
+    }
+
+    /// Pay `payment` from wallet-owned single-key outputs,
+    /// returning change to the wallet address
+    fn create_payment(
+        &mut self,
+        utxos: &UTXOSet,
+        payment: TxOutput,
+        current_height: u64,
+        lock_time: u64,
+        fee_per_byte: u64,
     ) -> Result<Transaction, &'static str> {
         let master = self.master_seed.ok_or("wallet locked")?;
 
+        let v6 = consensus_version_for_height(current_height) >= ConsensusVersion::V6;
+        if lock_time != 0 && !v6 {
+            return Err("lock_time requires consensus v6");
+        }
+        if payment.kind != OutputKind::PubkeyHash && !v6 {
+            return Err("output type requires consensus v6");
+        }
+
+        let amount = payment.value;
         let mut collected = 0u64;
+        let mut fee = estimated_fee(0, 2, fee_per_byte);
         let mut inputs = Vec::new();
 
         for (key, utxo) in utxos {
-            for index in 0..20 {
-                let child = derive_child_seed(&master, index);
-                let sk = secret_key_from_seed(&child);
-                let pk = public_key(&sk);
+            if utxo.kind != OutputKind::PubkeyHash {
+                continue;
+            }
 
-                if pubkey_hash(&pk) == utxo.pubkey_hash {
-                    let parts: Vec<&str> = key.split(':').collect();
-                    let txid = hex::decode(parts[0]).unwrap();
-                    let vout = parts[1].parse().unwrap();
+            if let Some(index) = key_index_for(&master, &utxo.pubkey_hash) {
+                let parts: Vec<&str> = key.split(':').collect();
+                let txid = hex::decode(parts[0]).unwrap();
+                let vout = parts[1].parse().unwrap();
 
-                    inputs.push((txid, vout, index, utxo.value));
-                    collected += utxo.value;
+                inputs.push((txid, vout, index, utxo.value));
+                collected += utxo.value;
+                fee = estimated_fee(inputs.len(), 2, fee_per_byte);
 
-                    if collected >= amount {
-                        break;
-                    }
+                if collected >= amount + fee {
+                    break;
                 }
             }
-            if collected >= amount {
-                break;
-            }
         }
 
-        if collected < amount {
+        if collected < amount + fee {
             return Err("insufficient funds");
         }
 
-        let mut outputs = vec![TxOutput {
-            value: amount,
-            pubkey_hash: to,
-        }];
+        let mut outputs = vec![payment];
 
-        if collected > amount {
-            outputs.push(TxOutput {
-                value: collected - amount,
-                pubkey_hash: self.address()?,
-            });
+        // Dust change is left to the fee (non-standard otherwise)
+        let change = collected - amount - fee;
+        if change >= self.policy.dust_threshold() {
+            outputs.push(TxOutput::pay_to_pubkey_hash(change, self.address()?));
         }
 
         let mut tx = Transaction {
+            version: if v6 { TX_VERSION_LOCKTIME } else { TX_VERSION_LEGACY },
             inputs: Vec::new(),
             outputs,
+            lock_time,
         };
 
-        let sighash = tx.sighash();
+        if !v6 {
+            let sighash = tx.sighash();
 
-        for (txid, vout, index, _) in inputs {
-            let child = derive_child_seed(&master, index);
-            let sk = secret_key_from_seed(&child);
-            let pk = public_key(&sk);
+            for (txid, vout, index, _) in inputs {
+                let child = derive_child_seed(&master, index);
+                let sk = secret_key_from_seed(&child);
+                let pk = public_key(&sk);
 
+                tx.inputs.push(TxInput {
+                    txid,
+                    index: vout,
+                    signature: sign(&sighash, &sk),
+                    pubkey: pk.serialize().to_vec(),
+                    address_index: index,
+                    signatures: Vec::new(),
+                    preimage: Vec::new(),
+                });
+            }
+
+            return Ok(tx);
+        }
+
+        for (txid, vout, index, _) in inputs {
             tx.inputs.push(TxInput {
                 txid,
                 index: vout,
-                signature: sign(&sighash, &sk),
-                pubkey: pk.serialize().to_vec(),
+                pubkey: Vec::new(),
+                signature: Vec::new(),
                 address_index: index,
+                signatures: Vec::new(),
+                preimage: Vec::new(),
+            });
+        }
+
+        sign_inputs(&master, &mut tx);
+
+        Ok(tx)
+    }
+
+    /// Replace an unconfirmed wallet transaction with a
+    /// higher-fee copy (replace-by-fee)
+    ///
+    /// Same inputs and payments; the extra fee comes out of the
+    /// change output. `prevouts` must contain every spent output.
+    /// The new fee is at least `fee_per_byte` and beats the old
+    /// fee by INCREMENTAL_RELAY_FEE_PER_BYTE per byte.
+    pub fn bump_fee(
+        &mut self,
+        original: &Transaction,
+        prevouts: &UTXOSet,
+        fee_per_byte: u64,
+    ) -> Result<Transaction, &'static str> {
+        let master = self.master_seed.ok_or("wallet locked")?;
+
+        let mut input_sum = 0u64;
+        let mut indices = Vec::new();
+
+        for input in &original.inputs {
+            let utxo = prevouts
+                .get(&outpoint_key(&input.txid, input.index))
+                .ok_or("spent output not found")?;
+
+            if utxo.kind != OutputKind::PubkeyHash {
+                return Err("only single-key inputs can be re-signed");
+            }
+
+            indices.push(key_index_for(&master, &utxo.pubkey_hash).ok_or("input not owned by wallet")?);
+            input_sum += utxo.value;
+        }
+
+        let output_sum: u64 = original.outputs.iter().map(|o| o.value).sum();
+        let old_fee = input_sum.checked_sub(output_sum).ok_or("outputs exceed inputs")?;
+
+        let size = original.serialized_size() as u64;
+        let new_fee = (size * fee_per_byte)
+            .max(old_fee + size * INCREMENTAL_RELAY_FEE_PER_BYTE as u64);
+        let extra = new_fee - old_fee;
+
+        let change = original
+            .outputs
+            .iter()
+            .rposition(|o| {
+                o.kind == OutputKind::PubkeyHash
+                    && key_index_for(&master, &o.pubkey_hash).is_some()
+            })
+            .ok_or("no change output to reduce")?;
+
+        let mut tx = original.clone();
+
+        if tx.outputs[change].value < extra + self.policy.dust_threshold() {
+            return Err("change too small to bump fee");
+        }
+        tx.outputs[change].value -= extra;
+
+        for (input, index) in tx.inputs.iter_mut().zip(indices) {
+            input.address_index = index;
+            input.pubkey = Vec::new();
+            input.signature = Vec::new();
+        }
+
+        if tx.version != TX_VERSION_LEGACY {
+            sign_inputs(&master, &mut tx);
+            return Ok(tx);
+        }
+
+        // Legacy sighash is computed before inputs are attached
+        let inputs = std::mem::take(&mut tx.inputs);
+        let sighash = tx.sighash();
+
+        for mut input in inputs {
+            let child = derive_child_seed(&master, input.address_index);
+            let sk = secret_key_from_seed(&child);
+
+            input.pubkey = public_key(&sk).serialize().to_vec();
+            input.signature = sign(&sighash, &sk);
+            tx.inputs.push(input);
+        }
+
+        Ok(tx)
+    }
+
+    /// Start an unsigned spend of a multisig output (CONSENSUS v6)
+    ///
+    /// Pays `amount` to `to`; any remainder above the fee
+    /// returns to the same m-of-n policy.
+    pub fn create_multisig_spend(
+        &self,
+        utxos: &UTXOSet,
+        outpoint: &str,
+        to: Vec<u8>,
+        amount: u64,
+        current_height: u64,
+    ) -> Result<PartialTransaction, &'static str> {
+        if consensus_version_for_height(current_height) < ConsensusVersion::V6 {
+            return Err("multisig requires consensus v6");
+        }
+
+        let utxo = utxos.get(outpoint).ok_or("unknown outpoint")?;
+
+        let required = match &utxo.kind {
+            OutputKind::Multisig { required, .. } => *required,
+            _ => return Err("outpoint is not multisig"),
+        };
+
+        let (txid, vout) = parse_outpoint(outpoint)?;
+
+        let fee = estimated_fee(1, 2, self.fee_per_byte)
+            + required as u64 * 100 * self.fee_per_byte;
+
+        if utxo.value < amount + fee {
+            return Err("insufficient funds");
+        }
+
+        let mut outputs = vec![TxOutput::pay_to_pubkey_hash(amount, to)];
+
+        if utxo.value - amount - fee >= self.policy.dust_threshold() {
+            outputs.push(TxOutput {
+                value: utxo.value - amount - fee,
+                pubkey_hash: Vec::new(),
+                kind: utxo.kind.clone(),
             });
         }
 
+        let tx = Transaction {
+            version: TX_VERSION_LOCKTIME,
+            inputs: vec![TxInput {
+                txid,
+                index: vout,
+                pubkey: Vec::new(),
+                signature: Vec::new(),
+                address_index: 0,
+                signatures: Vec::new(),
+                preimage: Vec::new(),
+            }],
+            outputs,
+            lock_time: 0,
+        };
+
+        Ok(PartialTransaction {
+            version: PARTIAL_TX_VERSION,
+            tx,
+            prevouts: vec![utxo.clone()],
+        })
+    }
+
+    /// Lock `amount` in an HTLC (CONSENSUS v6)
+    ///
+    /// Claimable by `claim_pubkey_hash` with the preimage of
+    /// `payment_hash`, refundable to this wallet from `timeout_height`.
+    pub fn create_htlc(
+        &mut self,
+        utxos: &UTXOSet,
+        payment_hash: Vec<u8>,
+        claim_pubkey_hash: Vec<u8>,
+        timeout_height: u64,
+        amount: u64,
+        current_height: u64,
+    ) -> Result<Transaction, &'static str> {
+        if payment_hash.len() != 32 || claim_pubkey_hash.len() != 32 {
+            return Err("hashes must be 32 bytes");
+        }
+        if timeout_height <= current_height {
+            return Err("timeout must be in the future");
+        }
+
+        let refund_pubkey_hash = self.address()?;
+
+        self.create_payment(
+            utxos,
+            TxOutput::htlc(
+                amount,
+                payment_hash,
+                claim_pubkey_hash,
+                refund_pubkey_hash,
+                timeout_height,
+            ),
+            current_height,
+            0,
+            self.fee_per_byte,
+        )
+    }
+
+    /// Claim an HTLC by revealing its preimage
+    pub fn claim_htlc(
+        &self,
+        utxos: &UTXOSet,
+        outpoint: &str,
+        preimage: Vec<u8>,
+        current_height: u64,
+    ) -> Result<Transaction, &'static str> {
+        if preimage.len() != HTLC_PREIMAGE_SIZE {
+            return Err("preimage must be 32 bytes");
+        }
+        self.spend_htlc(utxos, outpoint, preimage, current_height)
+    }
+
+    /// Reclaim an expired HTLC via the refund key
+    pub fn refund_htlc(
+        &self,
+        utxos: &UTXOSet,
+        outpoint: &str,
+        current_height: u64,
+    ) -> Result<Transaction, &'static str> {
+        self.spend_htlc(utxos, outpoint, Vec::new(), current_height)
+    }
+
+    /// Spend an HTLC back to this wallet
+    /// (non-empty preimage = claim, empty = refund)
+    fn spend_htlc(
+        &self,
+        utxos: &UTXOSet,
+        outpoint: &str,
+        preimage: Vec<u8>,
+        current_height: u64,
+    ) -> Result<Transaction, &'static str> {
+        let master = self.master_seed.ok_or("wallet locked")?;
+
+        if consensus_version_for_height(current_height) < ConsensusVersion::V6 {
+            return Err("HTLC requires consensus v6");
+        }
+
+        let utxo = utxos.get(outpoint).ok_or("unknown outpoint")?;
+
+        let (payment_hash, claim_pubkey_hash, refund_pubkey_hash, timeout_height) =
+            match &utxo.kind {
+                OutputKind::Htlc {
+                    payment_hash,
+                    claim_pubkey_hash,
+                    refund_pubkey_hash,
+                    timeout_height,
+                } => (payment_hash, claim_pubkey_hash, refund_pubkey_hash, *timeout_height),
+                _ => return Err("outpoint is not an HTLC"),
+            };
+
+        let key_hash = if preimage.is_empty() {
+            if current_height < timeout_height {
+                return Err("HTLC has not timed out yet");
+            }
+            refund_pubkey_hash
+        } else {
+            if sha256(&preimage) != *payment_hash {
+                return Err("preimage does not match payment hash");
+            }
+            claim_pubkey_hash
+        };
+
+        let index = key_index_for(&master, key_hash).ok_or("HTLC key not in wallet")?;
+        let (txid, vout) = parse_outpoint(outpoint)?;
+
+        let fee = estimated_fee(1, 1, self.fee_per_byte);
+        if utxo.value <= fee {
+            return Err("HTLC value does not cover fee");
+        }
+
+        let mut tx = Transaction {
+            version: TX_VERSION_LOCKTIME,
+            inputs: vec![TxInput {
+                txid,
+                index: vout,
+                pubkey: Vec::new(),
+                signature: Vec::new(),
+                address_index: index,
+                signatures: Vec::new(),
+                preimage,
+            }],
+            outputs: vec![TxOutput::pay_to_pubkey_hash(
+                utxo.value - fee,
+                self.address()?,
+            )],
+            lock_time: 0,
+        };
+
+        sign_inputs(&master, &mut tx);
+
         Ok(tx)
     }
+
+    /// Add this wallet's signatures to a partially-signed spend
+    ///
+    /// Returns the number of signatures added.
+    pub fn cosign(&self, partial: &mut PartialTransaction) -> Result<usize, &'static str> {
+        let master = self.master_seed.ok_or("wallet locked")?;
+
+        if partial.prevouts.len() != partial.tx.inputs.len() {
+            return Err("malformed partial transaction");
+        }
+
+        let sighash = partial.tx.sighash();
+        let mut added = 0;
+
+        for (input, prevout) in partial.tx.inputs.iter_mut().zip(&partial.prevouts) {
+            let (required, pubkey_hashes) = match &prevout.kind {
+                OutputKind::Multisig { required, pubkey_hashes } => (*required, pubkey_hashes),
+                _ => continue,
+            };
+
+            for hash in pubkey_hashes {
+                if input.signatures.len() >= required as usize {
+                    break;
+                }
+
+                let index = match key_index_for(&master, hash) {
+                    Some(i) => i,
+                    None => continue,
+                };
+
+                let child = derive_child_seed(&master, index);
+                let sk = secret_key_from_seed(&child);
+                let pubkey = public_key(&sk).serialize().to_vec();
+
+                if input.signatures.iter().any(|s| s.pubkey == pubkey) {
+                    continue;
+                }
+
+                input.signatures.push(InputSignature {
+                    pubkey,
+                    signature: sign(&sighash, &sk),
+                });
+                added += 1;
+            }
+        }
+
+        Ok(added)
+    }
+}
+
+/// Parse "txid_hex:index"
+fn parse_outpoint(outpoint: &str) -> Result<(Vec<u8>, u32), &'static str> {
+    let parts: Vec<&str> = outpoint.split(':').collect();
+    if parts.len() != 2 {
+        return Err("invalid outpoint");
+    }
+    let txid = hex::decode(parts[0]).map_err(|_| "invalid outpoint")?;
+    let vout: u32 = parts[1].parse().map_err(|_| "invalid outpoint")?;
+    Ok((txid, vout))
+}
+
+/// Preimage revealed by a confirmed HTLC claim
+///
+/// The counterparty of a swap learns the secret here and
+/// uses it to claim the matching HTLC on the other chain.
+pub fn find_htlc_preimage(blocks: &[Block], outpoint: &str) -> Option<Vec<u8>> {
+    let (txid, vout) = parse_outpoint(outpoint).ok()?;
+
+    blocks
+        .iter()
+        .flat_map(|b| &b.transactions)
+        .flat_map(|tx| &tx.inputs)
+        .find(|i| i.txid == txid && i.index == vout && !i.preimage.is_empty())
+        .map(|i| i.preimage.clone())
+}
+
+/* ───────── Partially-Signed Transactions ───────── */
+
+/// Current PartialTransaction format
+pub const PARTIAL_TX_VERSION: u32 = 1;
+
+/// Partially-signed multisig spend (wallet exchange format)
+///
+/// Passed between co-signers as JSON. Each signer adds
+/// signatures for the keys it holds; once every multisig
+/// input has `required` signatures it can be submitted.
+#[derive(Serialize, Deserialize)]
+pub struct PartialTransaction {
+    pub version: u32,
+    pub tx: Transaction,
+
+    /// Outputs being spent, same order as tx.inputs
+    pub prevouts: Vec<UTXO>,
+}
+
+impl PartialTransaction {
+    /// (signatures present, signatures required) per input
+    pub fn signature_status(&self) -> Vec<(usize, u32)> {
+        self.tx
+            .inputs
+            .iter()
+            .zip(&self.prevouts)
+            .map(|(input, prevout)| match &prevout.kind {
+                OutputKind::Multisig { required, .. } => (input.signatures.len(), *required),
+                _ => (input.signatures.len(), 0),
+            })
+            .collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.signature_status()
+            .iter()
+            .all(|(have, need)| *have >= *need as usize)
+    }
+
+    pub fn load(path: &str) -> Result<Self, &'static str> {
+        let data = fs::read_to_string(path).map_err(|_| "cannot read partial transaction")?;
+        serde_json::from_str(&data).map_err(|_| "invalid partial transaction")
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), &'static str> {
+        let data = serde_json::to_string_pretty(self).map_err(|_| "serialize failed")?;
+        fs::write(path, data).map_err(|_| "cannot write partial transaction")
+    }
 }
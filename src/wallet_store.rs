@@ -12,6 +12,12 @@ pub struct WalletStore {
     pub wallets: HashMap<String, String>,
 }
 
+impl Default for WalletStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl WalletStore {
     pub fn new() -> Self {
         Self {